@@ -0,0 +1,60 @@
+use crate::Val;
+
+/// Marks attributes as unknown during evaluation, e.g. `request.auth.*`.
+///
+/// A pattern is a variable name followed by dot-separated qualifiers, where `*`
+/// matches any qualifier. An attribute is unknown when its qualifiers and the
+/// pattern's agree for as far as both go, so `request.auth` covers
+/// `request.auth.claims` as well as `request` itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributePattern {
+    variable: String,
+    qualifiers: Vec<Option<String>>,
+}
+
+impl AttributePattern {
+    pub fn new(pattern: &str) -> Self {
+        let mut segments = pattern.split('.');
+        let variable = segments.next().unwrap_or_default().to_string();
+        let qualifiers = segments
+            .map(|q| if q == "*" { None } else { Some(q.to_string()) })
+            .collect();
+        Self {
+            variable,
+            qualifiers,
+        }
+    }
+
+    pub(crate) fn matches(&self, variable: &str, qualifiers: &[Val]) -> bool {
+        // The later segments of a qualified variable name, like `b` in `a.b`, are
+        // matched as qualifiers.
+        let mut segments = variable.split('.');
+        if segments.next() != Some(self.variable.as_str()) {
+            return false;
+        }
+        let qualifiers = segments.map(Val::new_string).chain(qualifiers.iter().cloned());
+        self.qualifiers
+            .iter()
+            .zip(qualifiers)
+            .all(|(pattern, qualifier)| match pattern {
+                None => true,
+                Some(p) => qualifier_matches(p, &qualifier),
+            })
+    }
+}
+
+fn qualifier_matches(pattern: &str, qualifier: &Val) -> bool {
+    if let Some(s) = qualifier.as_string() {
+        return s == pattern;
+    }
+    if let Some(i) = qualifier.as_int() {
+        return i.to_string() == pattern;
+    }
+    if let Some(u) = qualifier.as_uint() {
+        return u.to_string() == pattern;
+    }
+    if let Some(b) = qualifier.as_bool() {
+        return b.to_string() == pattern;
+    }
+    false
+}
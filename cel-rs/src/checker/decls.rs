@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use super::types::Type;
+use crate::function::Function;
+
+/// A single overload signature the checker can match call arguments against.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub args: Vec<Type>,
+    pub result: Type,
+}
+
+impl Signature {
+    fn new(args: Vec<Type>, result: Type) -> Self {
+        Self { args, result }
+    }
+}
+
+/// Signatures of a function registered on the context, derived from its overloads.
+pub fn signatures(func: &Function) -> Vec<Signature> {
+    func.overloads
+        .iter()
+        .map(|o| Signature::new(o.args.iter().map(Type::from).collect(), Type::from(&o.result)))
+        .collect()
+}
+
+pub const ADD: &str = "_+_";
+pub const SUBTRACT: &str = "_-_";
+pub const MULTIPLY: &str = "_*_";
+pub const DIVIDE: &str = "_/_";
+pub const MODULO: &str = "_%_";
+pub const LESS: &str = "_<_";
+pub const LESS_EQUALS: &str = "_<=_";
+pub const GREATER: &str = "_>_";
+pub const GREATER_EQUALS: &str = "_>=_";
+pub const EQUALS: &str = "_==_";
+pub const NOT_EQUALS: &str = "_!=_";
+pub const IN: &str = "@in";
+pub const LOGICAL_NOT: &str = "!_";
+pub const NEGATE: &str = "-_";
+pub const LOGICAL_AND: &str = "_&&_";
+pub const LOGICAL_OR: &str = "_||_";
+pub const CONDITIONAL: &str = "_?_:_";
+pub const INDEX: &str = "_[_]";
+
+fn binary(args: &[(Type, Type, Type)]) -> Vec<Signature> {
+    args.iter()
+        .map(|(l, r, result)| Signature::new(vec![l.clone(), r.clone()], result.clone()))
+        .collect()
+}
+
+fn comparisons() -> Vec<Signature> {
+    let mut sigs = binary(&[
+        (Type::Bool, Type::Bool, Type::Bool),
+        (Type::String, Type::String, Type::Bool),
+        (Type::Bytes, Type::Bytes, Type::Bool),
+        (Type::Timestamp, Type::Timestamp, Type::Bool),
+        (Type::Duration, Type::Duration, Type::Bool),
+    ]);
+    // Numbers are ordered by value whatever their types, so every pair is declared.
+    let numbers = [Type::Int, Type::UInt, Type::Double];
+    for l in numbers.iter() {
+        for r in numbers.iter() {
+            sigs.push(Signature::new(vec![l.clone(), r.clone()], Type::Bool));
+        }
+    }
+    sigs
+}
+
+lazy_static::lazy_static! {
+    /// Signatures of the operators the evaluator implements natively.
+    pub static ref OPERATORS: HashMap<&'static str, Vec<Signature>> = {
+        let a = || Type::Param("A");
+        let b = || Type::Param("B");
+        let arithmetic = binary(&[
+            (Type::Int, Type::Int, Type::Int),
+            (Type::UInt, Type::UInt, Type::UInt),
+            (Type::Double, Type::Double, Type::Double),
+        ]);
+
+        let mut add = arithmetic.clone();
+        add.extend(binary(&[
+            (Type::String, Type::String, Type::String),
+            (Type::Bytes, Type::Bytes, Type::Bytes),
+            (Type::list(a()), Type::list(a()), Type::list(a())),
+            (Type::Timestamp, Type::Duration, Type::Timestamp),
+            (Type::Duration, Type::Timestamp, Type::Timestamp),
+            (Type::Duration, Type::Duration, Type::Duration),
+        ]));
+
+        let mut subtract = arithmetic.clone();
+        subtract.extend(binary(&[
+            (Type::Timestamp, Type::Duration, Type::Timestamp),
+            (Type::Timestamp, Type::Timestamp, Type::Duration),
+            (Type::Duration, Type::Duration, Type::Duration),
+        ]));
+
+        HashMap::from([
+            (ADD, add),
+            (SUBTRACT, subtract),
+            (MULTIPLY, arithmetic.clone()),
+            (DIVIDE, arithmetic),
+            (MODULO, binary(&[
+                (Type::Int, Type::Int, Type::Int),
+                (Type::UInt, Type::UInt, Type::UInt),
+            ])),
+            (LESS, comparisons()),
+            (LESS_EQUALS, comparisons()),
+            (GREATER, comparisons()),
+            (GREATER_EQUALS, comparisons()),
+            (EQUALS, binary(&[(a(), a(), Type::Bool)])),
+            (NOT_EQUALS, binary(&[(a(), a(), Type::Bool)])),
+            (IN, binary(&[
+                (a(), Type::list(a()), Type::Bool),
+                (a(), Type::map(a(), b()), Type::Bool),
+            ])),
+            (LOGICAL_NOT, vec![Signature::new(vec![Type::Bool], Type::Bool)]),
+            (NEGATE, vec![
+                Signature::new(vec![Type::Int], Type::Int),
+                Signature::new(vec![Type::Double], Type::Double),
+                Signature::new(vec![Type::Duration], Type::Duration),
+            ]),
+            (LOGICAL_AND, binary(&[(Type::Bool, Type::Bool, Type::Bool)])),
+            (LOGICAL_OR, binary(&[(Type::Bool, Type::Bool, Type::Bool)])),
+            (CONDITIONAL, vec![Signature::new(vec![Type::Bool, a(), a()], a())]),
+            (INDEX, binary(&[
+                (Type::list(a()), Type::Int, a()),
+                (Type::map(a(), b()), a(), b()),
+            ])),
+        ])
+    };
+}
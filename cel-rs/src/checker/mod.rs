@@ -0,0 +1,435 @@
+mod decls;
+mod types;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::parser::{ArithmeticOp, Atom, Comprehension, ExprId, ExprKind, Expression, Member, RelationOp, Span, UnaryOp};
+use crate::Context;
+use decls::Signature;
+pub use types::Type;
+
+/// A problem found while checking, attached to the offending sub-expression.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub id: ExprId,
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct CheckError {
+    source: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl CheckError {
+    pub(crate) fn new(source: &str, diagnostics: Vec<Diagnostic>) -> Self {
+        Self {
+            source: source.to_string(),
+            diagnostics,
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diag) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let (line, column) = diag.span.location(&self.source);
+            writeln!(f, "ERROR: <input>:{}:{}: {}", line, column, diag.message)?;
+            writeln!(f, " | {}", self.source.lines().nth(line - 1).unwrap_or_default())?;
+            write!(f, " | {}^", ".".repeat(column - 1))?;
+        }
+        Ok(())
+    }
+}
+
+/// Type-checks a parsed expression against the variables and functions of a [`Context`].
+pub struct Checker<'a> {
+    ctx: &'a Context,
+    diagnostics: Vec<Diagnostic>,
+    /// Variables bound by the enclosing comprehensions, innermost last.
+    scopes: Vec<HashMap<String, Type>>,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(ctx: &'a Context) -> Self {
+        Self {
+            ctx,
+            diagnostics: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).cloned())
+    }
+
+    /// Returns the type of the expression, or every diagnostic found along the way.
+    pub fn check(mut self, expr: &Expression) -> Result<Type, Vec<Diagnostic>> {
+        let ty = self.check_expr(expr);
+        if self.diagnostics.is_empty() {
+            Ok(ty)
+        } else {
+            Err(self.diagnostics)
+        }
+    }
+
+    fn report(&mut self, expr: &Expression, message: String) -> Type {
+        self.report_at(expr, expr.span, message)
+    }
+
+    /// Reports a problem with `expr` at a narrower `span`, such as its operator.
+    fn report_at(&mut self, expr: &Expression, span: Span, message: String) -> Type {
+        self.diagnostics.push(Diagnostic { id: expr.id, span, message });
+        Type::Error
+    }
+
+    fn check_expr(&mut self, expr: &Expression) -> Type {
+        match &expr.kind {
+            ExprKind::Atom(atom) => match atom {
+                Atom::Int(_) => Type::Int,
+                Atom::UInt(_) => Type::UInt,
+                Atom::Float(_) => Type::Double,
+                Atom::String(_) => Type::String,
+                Atom::Bytes(_) => Type::Bytes,
+                Atom::Bool(_) => Type::Bool,
+                Atom::Null => Type::Null,
+            },
+            ExprKind::Ident(name) => match self.local(name).or_else(|| self.ctx.resolve_variable_type(name)) {
+                Some(ty) => ty,
+                None => {
+                    let suggestion = suggest(name, self.ctx.variable_names());
+                    self.report(expr, undeclared(name, suggestion))
+                }
+            },
+            ExprKind::GlobalFunctionCall(name, argexprs) => {
+                let args = argexprs.iter().map(|a| self.check_expr(a)).collect();
+                self.check_call(expr, name, args, false)
+            }
+            ExprKind::Member(operand, member) => {
+                if let Member::Fields(fields) = member.as_ref() {
+                    return self.check_construction(expr, operand, fields);
+                }
+                // The whole dotted name may itself be a variable, e.g. `a.b.c`, unless
+                // it starts with a local variable.
+                let qualified = expr
+                    .qualified_name()
+                    .filter(|name| self.local(name.split('.').next().unwrap_or_default()).is_none());
+                if let Some(ty) = qualified.and_then(|name| self.ctx.resolve_variable_type(&name)) {
+                    return ty;
+                }
+                let shadowed = operand
+                    .qualified_name()
+                    .is_some_and(|name| self.local(name.split('.').next().unwrap_or_default()).is_some());
+                if let (Member::FunctionCall(name, argexprs), false) = (member.as_ref(), shadowed) {
+                    if let Some(function) = self.ctx.namespaced_function(operand, name) {
+                        let args = argexprs.iter().map(|a| self.check_expr(a)).collect();
+                        return self.check_call(expr, &function, args, false);
+                    }
+                }
+                let operand_ty = self.check_expr(operand);
+                match member.as_ref() {
+                    Member::Attribute(field) => self.check_select(expr, field, operand_ty),
+                    Member::FunctionCall(name, argexprs) => {
+                        let mut args = vec![operand_ty];
+                        args.extend(argexprs.iter().map(|a| self.check_expr(a)));
+                        self.check_call(expr, name, args, true)
+                    }
+                    Member::Index(index) => {
+                        let index_ty = self.check_expr(index);
+                        self.check_operator(expr, expr.span, decls::INDEX, vec![operand_ty, index_ty])
+                    }
+                    Member::Fields(_) => unreachable!("construction is checked above"),
+                }
+            }
+            ExprKind::Arithmetic(left, op, op_span, right) => {
+                let args = vec![self.check_expr(left), self.check_expr(right)];
+                let name = match op {
+                    ArithmeticOp::Add => decls::ADD,
+                    ArithmeticOp::Subtract => decls::SUBTRACT,
+                    ArithmeticOp::Multiply => decls::MULTIPLY,
+                    ArithmeticOp::Divide => decls::DIVIDE,
+                    ArithmeticOp::Modulus => decls::MODULO,
+                };
+                self.check_operator(expr, *op_span, name, args)
+            }
+            ExprKind::Relation(left, op, op_span, right) => {
+                let args = vec![self.check_expr(left), self.check_expr(right)];
+                let name = match op {
+                    RelationOp::LessThan => decls::LESS,
+                    RelationOp::LessThanEq => decls::LESS_EQUALS,
+                    RelationOp::GreaterThan => decls::GREATER,
+                    RelationOp::GreaterThanEq => decls::GREATER_EQUALS,
+                    RelationOp::Equals => decls::EQUALS,
+                    RelationOp::NotEquals => decls::NOT_EQUALS,
+                    RelationOp::In => decls::IN,
+                };
+                self.check_operator(expr, *op_span, name, args)
+            }
+            ExprKind::Ternary(condition, left, right) => {
+                let args = vec![
+                    self.check_expr(condition),
+                    self.check_expr(left),
+                    self.check_expr(right),
+                ];
+                self.check_operator(expr, expr.span, decls::CONDITIONAL, args)
+            }
+            ExprKind::Or(left, right) => {
+                let args = vec![self.check_expr(left), self.check_expr(right)];
+                self.check_operator(expr, expr.span, decls::LOGICAL_OR, args)
+            }
+            ExprKind::And(left, right) => {
+                let args = vec![self.check_expr(left), self.check_expr(right)];
+                self.check_operator(expr, expr.span, decls::LOGICAL_AND, args)
+            }
+            ExprKind::Unary(op, operand) => {
+                let args = vec![self.check_expr(operand)];
+                let name = match op {
+                    UnaryOp::Not | UnaryOp::DoubleNot => decls::LOGICAL_NOT,
+                    UnaryOp::Minus | UnaryOp::DoubleMinus => decls::NEGATE,
+                };
+                self.check_operator(expr, expr.span, name, args)
+            }
+            ExprKind::List(elems) => {
+                let types: Vec<Type> = elems.iter().map(|e| self.check_expr(e)).collect();
+                self.check_homogeneous(elems.iter(), &types);
+                Type::list(join(types))
+            }
+            ExprKind::Map(entries) => {
+                let (keys, values): (Vec<Type>, Vec<Type>) = entries
+                    .iter()
+                    .map(|(k, v)| (self.check_expr(k), self.check_expr(v)))
+                    .unzip();
+                self.check_homogeneous(entries.iter().map(|(k, _)| k), &keys);
+                self.check_homogeneous(entries.iter().map(|(_, v)| v), &values);
+                Type::map(join(keys), join(values))
+            }
+            ExprKind::Has(operand, field) => {
+                let operand_ty = self.check_expr(operand);
+                match self.check_select(expr, field, operand_ty) {
+                    Type::Error => Type::Error,
+                    _ => Type::Bool,
+                }
+            }
+            ExprKind::Comprehension(c) => self.check_comprehension(c),
+        }
+    }
+
+    fn check_comprehension(&mut self, c: &Comprehension) -> Type {
+        let range = self.check_expr(&c.iter_range);
+        // The types of the two iteration variables, and the single one's.
+        let (first, second, single) = match range {
+            Type::List(elem) => (Type::Int, *elem.clone(), *elem),
+            Type::Map(key, value) => (*key.clone(), *value, *key),
+            Type::Dyn | Type::Error => (range.clone(), range.clone(), range),
+            other => {
+                let message = format!(
+                    "expression of type '{}' cannot be the range of a comprehension (must be list, map, or dynamic)",
+                    other
+                );
+                return self.report(&c.iter_range, message);
+            }
+        };
+        let mut accu = self.check_expr(&c.accu_init);
+
+        let mut scope = HashMap::from([(c.accu_var.to_string(), accu.clone())]);
+        match &c.iter_var2 {
+            Some(var2) => {
+                scope.insert(c.iter_var.to_string(), first);
+                scope.insert(var2.to_string(), second);
+            }
+            None => {
+                scope.insert(c.iter_var.to_string(), single);
+            }
+        }
+        self.scopes.push(scope);
+        self.check_expr(&c.loop_condition);
+        let step = self.check_expr(&c.loop_step);
+        self.scopes.pop();
+        // A step such as `accu + [x]` narrows an empty list's `list(dyn)`.
+        if step != Type::Error && accu.is_assignable(&step, &mut HashMap::new()) {
+            accu = step;
+        }
+
+        self.scopes.push(HashMap::from([(c.accu_var.to_string(), accu)]));
+        let result = self.check_expr(&c.result);
+        self.scopes.pop();
+        result
+    }
+
+    /// With homogeneous aggregate literals enabled, every member of a list or map
+    /// literal must have the type of the first one; `dyn(...)` opts a member out.
+    fn check_homogeneous<'e>(&mut self, exprs: impl Iterator<Item = &'e Expression>, types: &[Type]) {
+        if !self.ctx.homogeneous_aggregate_literals_enabled() {
+            return;
+        }
+        let Some(expected) = types.first() else {
+            return;
+        };
+        for (expr, ty) in exprs.zip(types.iter()).skip(1) {
+            if !expected.is_assignable(ty, &mut HashMap::new()) {
+                self.report(expr, format!("expected type '{}' but found '{}'", expected, ty));
+            }
+        }
+    }
+
+    fn check_select(&mut self, expr: &Expression, field: &str, operand: Type) -> Type {
+        match operand {
+            Type::Dyn | Type::Error => operand,
+            Type::Map(_, value) => *value,
+            Type::Message(name) => match self.ctx.type_provider().and_then(|p| p.field_type(&name, field)) {
+                Some(ty) => ty,
+                None => self.report(expr, format!("undefined field '{}'", field)),
+            },
+            other => self.report(
+                expr,
+                format!(
+                    "unexpected type for field '{}': type '{}' does not support field selection",
+                    field, other
+                ),
+            ),
+        }
+    }
+
+    fn check_construction(&mut self, expr: &Expression, operand: &Expression, fields: &[(Rc<String>, Expression)]) -> Type {
+        let name = operand.qualified_name().unwrap_or_default();
+        let Some(type_name) = self.ctx.resolve_type(&name) else {
+            for (_, value) in fields {
+                self.check_expr(value);
+            }
+            return self.report(expr, undeclared(&name, None));
+        };
+        let provider = self.ctx.type_provider().unwrap();
+        for (field, value) in fields {
+            let value_ty = self.check_expr(value);
+            match provider.field_type(&type_name, field) {
+                Some(field_ty) if field_ty.is_assignable(&value_ty, &mut HashMap::new()) => {}
+                Some(field_ty) if value_ty == Type::Null && matches!(field_ty, Type::Message(_)) => {}
+                Some(field_ty) => {
+                    self.report(
+                        value,
+                        format!("expected type of field '{}' is '{}' but provided type is '{}'", field, field_ty, value_ty),
+                    );
+                }
+                None => {
+                    self.report(value, format!("undefined field '{}'", field));
+                }
+            }
+        }
+        Type::Message(type_name)
+    }
+
+    fn check_call(&mut self, expr: &Expression, name: &str, args: Vec<Type>, receiver: bool) -> Type {
+        match self.ctx.resolve_functions(name) {
+            Some(functions) => {
+                let sigs: Vec<Signature> = functions.iter().flat_map(decls::signatures).collect();
+                self.resolve_overload(expr, expr.span, name, &sigs, args, receiver)
+            }
+            None => {
+                let suggestion = suggest(name, self.ctx.function_names());
+                self.report(expr, undeclared(name, suggestion))
+            }
+        }
+    }
+
+    fn check_operator(&mut self, expr: &Expression, span: Span, name: &str, args: Vec<Type>) -> Type {
+        self.resolve_overload(expr, span, name, &decls::OPERATORS[name], args, false)
+    }
+
+    fn resolve_overload(
+        &mut self,
+        expr: &Expression,
+        span: Span,
+        name: &str,
+        sigs: &[Signature],
+        args: Vec<Type>,
+        receiver: bool,
+    ) -> Type {
+        // An argument that already failed has been reported; don't pile on.
+        if args.contains(&Type::Error) {
+            return Type::Error;
+        }
+
+        let mut results: Vec<Type> = Vec::new();
+        for sig in sigs.iter().filter(|s| s.args.len() == args.len()) {
+            let mut params = HashMap::new();
+            if sig.args.iter().zip(args.iter()).all(|(p, a)| p.is_assignable(a, &mut params)) {
+                results.push(sig.result.substitute(&params));
+            }
+        }
+
+        match results.first() {
+            None => {
+                let applied = if receiver {
+                    format!("{}.({})", args[0], format_types(&args[1..]))
+                } else {
+                    format!("({})", format_types(&args))
+                };
+                self.report_at(
+                    expr,
+                    span,
+                    format!("found no matching overload for '{}' applied to '{}'", name, applied),
+                )
+            }
+            Some(first) if results.iter().all(|r| r == first) => first.clone(),
+            Some(_) => Type::Dyn,
+        }
+    }
+}
+
+/// Common type of aggregate members, `dyn` when they disagree.
+fn join(types: Vec<Type>) -> Type {
+    match types.first() {
+        Some(first) if types.iter().all(|t| t == first) => first.clone(),
+        _ => Type::Dyn,
+    }
+}
+
+fn format_types(types: &[Type]) -> String {
+    types.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+fn undeclared(name: &str, suggestion: Option<&str>) -> String {
+    match suggestion {
+        Some(s) => format!("undeclared reference to '{}' (did you mean '{}'?)", name, s),
+        None => format!("undeclared reference to '{}'", name),
+    }
+}
+
+/// Closest candidate within a third of the name's length in edits.
+fn suggest<'n>(name: &str, candidates: impl Iterator<Item = &'n str>) -> Option<&'n str> {
+    let max = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= max)
+        .min()
+        .map(|(_, c)| c)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(cur).min(row[j])
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::value::ty::Ty;
+
+/// Static type of an expression as seen by the checker.
+///
+/// Unlike [`Ty`], which tags runtime values, checker types carry the element
+/// types of aggregates so that `list(int)` and `list(string)` can be told apart.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Type {
+    Null,
+    Bool,
+    Int,
+    UInt,
+    Double,
+    String,
+    Bytes,
+    Timestamp,
+    Duration,
+    List(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Optional(Box<Type>),
+    /// A type defined by an extension, such as `net.IP`.
+    Opaque(&'static str),
+    /// A message type of a [`TypeProvider`](crate::TypeProvider), by its full name.
+    Message(String),
+    Type,
+    Dyn,
+    Error,
+    /// Type parameter of a generic signature, e.g. `A` in `list(A)`.
+    Param(&'static str),
+}
+
+impl Type {
+    pub fn list(elem: Type) -> Self {
+        Type::List(Box::new(elem))
+    }
+
+    pub fn map(key: Type, value: Type) -> Self {
+        Type::Map(Box::new(key), Box::new(value))
+    }
+
+    pub fn optional(elem: Type) -> Self {
+        Type::Optional(Box::new(elem))
+    }
+
+    /// Reports whether a value of type `other` can be used where `self` is expected,
+    /// binding type parameters of `self` along the way.
+    pub(crate) fn is_assignable(&self, other: &Type, params: &mut HashMap<&'static str, Type>) -> bool {
+        match (self, other) {
+            (Type::Dyn | Type::Error, _) | (_, Type::Dyn | Type::Error) => true,
+            (Type::Param(name), _) => match params.get(name).cloned() {
+                Some(bound) => bound.is_assignable(other, params),
+                None => {
+                    params.insert(name, other.clone());
+                    true
+                }
+            },
+            (Type::List(a), Type::List(b)) | (Type::Optional(a), Type::Optional(b)) => a.is_assignable(b, params),
+            (Type::Map(ak, av), Type::Map(bk, bv)) => {
+                ak.is_assignable(bk, params) && av.is_assignable(bv, params)
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Replaces bound type parameters, falling back to `dyn` for unbound ones.
+    pub(crate) fn substitute(&self, params: &HashMap<&'static str, Type>) -> Type {
+        match self {
+            Type::Param(name) => params.get(name).cloned().unwrap_or(Type::Dyn),
+            Type::List(elem) => Type::list(elem.substitute(params)),
+            Type::Map(key, value) => Type::map(key.substitute(params), value.substitute(params)),
+            Type::Optional(elem) => Type::optional(elem.substitute(params)),
+            other => other.clone(),
+        }
+    }
+}
+
+impl From<&Ty> for Type {
+    fn from(ty: &Ty) -> Self {
+        match ty {
+            Ty::Int => Type::Int,
+            Ty::UInt => Type::UInt,
+            Ty::Double => Type::Double,
+            Ty::Bool => Type::Bool,
+            Ty::String => Type::String,
+            Ty::Bytes => Type::Bytes,
+            Ty::List => Type::list(Type::Dyn),
+            Ty::Map => Type::map(Type::Dyn, Type::Dyn),
+            Ty::Null => Type::Null,
+            Ty::Timestamp => Type::Timestamp,
+            Ty::Duration => Type::Duration,
+            Ty::Optional => Type::optional(Type::Dyn),
+            Ty::Opaque(name) => Type::Opaque(name),
+            Ty::Type => Type::Type,
+            Ty::Message | Ty::Unknown | Ty::Dyn => Type::Dyn,
+            Ty::Error => Type::Error,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Null => write!(f, "null_type"),
+            Type::Bool => write!(f, "bool"),
+            Type::Int => write!(f, "int"),
+            Type::UInt => write!(f, "uint"),
+            Type::Double => write!(f, "double"),
+            Type::String => write!(f, "string"),
+            Type::Bytes => write!(f, "bytes"),
+            Type::Timestamp => write!(f, "google.protobuf.Timestamp"),
+            Type::Duration => write!(f, "google.protobuf.Duration"),
+            Type::List(elem) => write!(f, "list({})", elem),
+            Type::Map(key, value) => write!(f, "map({}, {})", key, value),
+            Type::Optional(elem) => write!(f, "optional_type({})", elem),
+            Type::Opaque(name) => write!(f, "{}", name),
+            Type::Message(name) => write!(f, "{}", name),
+            Type::Type => write!(f, "type"),
+            Type::Dyn => write!(f, "dyn"),
+            Type::Error => write!(f, "*error*"),
+            Type::Param(name) => write!(f, "{}", name),
+        }
+    }
+}
@@ -1,42 +1,386 @@
-use crate::{function::Function, value::value::Val};
-use std::{collections::HashMap, rc::Rc};
+use crate::{attribute::AttributePattern, checker::Type, function::Function, parser::Expression, provider::{ProtoTypeProvider, TypeProvider, TypeProviders}, value::value::{Val, Value}};
+use prost_reflect::{DescriptorError, DescriptorPool, ReflectMessage};
+use std::{collections::HashMap, path::Path, rc::Rc};
 
+/// A set of functions, such as an extension, that can be added to a context at once.
+pub trait Library {
+    fn functions(&self) -> Vec<Function>;
+}
+
+/// The environment an expression is checked and evaluated in.
+///
+/// A context with a parent is a local scope: it only holds the variables bound in
+/// it, such as the iteration variable of a comprehension or a `cel.bind` name, and
+/// defers everything else to its parent.
 pub struct Context {
     par: Option<Rc<Context>>,
-    variables: HashMap<&'static str, Val>,
-    funtions: HashMap<&'static str, Function>,
+    variables: HashMap<String, Val>,
+    declarations: HashMap<&'static str, Type>,
+    /// Every function registered under a name; later ones take precedence.
+    funtions: HashMap<&'static str, Vec<Function>>,
+    homogeneous_aggregate_literals: bool,
+    unknowns: Vec<AttributePattern>,
+    container: String,
+    aliases: HashMap<String, String>,
+    /// The provider set with `set_type_provider`.
+    custom_type_provider: Option<Rc<dyn TypeProvider>>,
+    /// Descriptors registered with `add_file_descriptor_set`.
+    descriptors: Option<DescriptorPool>,
+    /// The enum values of `descriptors`, such as `pkg.Color.RED`, by full name.
+    enum_constants: HashMap<String, Val>,
+    /// The providers of `custom_type_provider` and `descriptors` together.
+    type_provider: Option<Rc<dyn TypeProvider>>,
 }
 
 impl Default for Context {
     fn default() -> Self {
+        let mut ctx = Self::empty();
+        for func in [
+            crate::std::new_dyn(),
+            crate::std::new_not_strictly_false(),
+            crate::std::new_map_insert(),
+            crate::std::new_size(),
+            crate::std::new_contains(),
+            crate::std::new_starts_with(),
+            crate::std::new_ends_with(),
+            crate::std::new_matches(),
+            crate::std::new_string(),
+            crate::std::new_int(),
+            crate::time::new_timestamp(),
+            crate::time::new_duration(),
+            crate::time::new_get_full_year(),
+            crate::time::new_get_month(),
+            crate::time::new_get_day_of_year(),
+            crate::time::new_get_day_of_month(),
+            crate::time::new_get_date(),
+            crate::time::new_get_day_of_week(),
+            crate::time::new_get_hours(),
+            crate::time::new_get_minutes(),
+            crate::time::new_get_seconds(),
+            crate::time::new_get_milliseconds(),
+        ] {
+            ctx.add_function(func.name, func);
+        }
+        ctx
+    }
+}
+
+impl Context {
+    fn empty() -> Self {
         Self {
             par: Default::default(),
             variables: Default::default(),
-            funtions: HashMap::from([
-                ("dyn", crate::std::new_dyn())
-            ]),
+            declarations: Default::default(),
+            funtions: Default::default(),
+            homogeneous_aggregate_literals: false,
+            unknowns: Default::default(),
+            container: Default::default(),
+            aliases: Default::default(),
+            custom_type_provider: None,
+            descriptors: None,
+            enum_constants: Default::default(),
+            type_provider: None,
         }
     }
-}
 
-impl Context {
+    /// The context at the top of the parent chain, which holds everything but local
+    /// variables.
+    fn root(&self) -> &Context {
+        match &self.par {
+            Some(par) => par.root(),
+            None => self,
+        }
+    }
+
+    /// Runs `f` in a new local scope below this context. The scope is the only
+    /// owner of its parent, so the parent is handed back once `f` returns.
+    pub(crate) fn scoped<R>(&mut self, f: impl FnOnce(&mut Context) -> R) -> R {
+        let parent = std::mem::replace(self, Context::empty());
+        let mut scope = Scope {
+            scope: Context { par: Some(Rc::new(parent)), ..Context::empty() },
+            context: self,
+        };
+        f(&mut scope.scope)
+    }
+    /// Binds a variable in this local scope.
+    pub(crate) fn bind(&mut self, name: &str, val: Val) {
+        self.variables.insert(name.to_string(), val);
+    }
+    /// A variable bound in a local scope, which shadows the root context's.
+    fn local(&self, name: &str) -> Option<&Val> {
+        let par = self.par.as_ref()?;
+        self.variables.get(name).or_else(|| par.local(name))
+    }
+
     pub fn add_variable(&mut self, name: &'static str, val: Val) -> &mut Self {
-        self.variables.insert(name, val);
+        self.variables.insert(name.to_string(), val);
         self
     }
     pub fn resolve_variable(&self, name: &String) -> Option<&Val> {
-        self.variables.get(name.as_str())
+        if let Some(local) = self.local(name) {
+            return Some(local);
+        }
+        self.candidate_names(name).iter().find_map(|c| self.root_variable(c))
+    }
+    /// A variable of the root context, or an enum constant standing in for one.
+    fn root_variable(&self, name: &str) -> Option<&Val> {
+        let root = self.root();
+        root.variables.get(name).or_else(|| root.enum_constants.get(name))
+    }
+
+    /// Splits `root.fields...` into the longest dotted name naming a variable and
+    /// the number of fields it takes up, so `a.b.c` can be the variable `a.b.c` or
+    /// field `c` of the variable `a.b`. The name is returned as resolved through the
+    /// container.
+    pub(crate) fn resolve_qualified_variable(&self, root: &str, fields: &[&str]) -> (String, usize) {
+        if self.local(root).is_some() {
+            return (root.to_string(), 0);
+        }
+        let mut name = root.to_string();
+        let mut longest = (root_scoped(root).to_string(), 0);
+        for i in 0..=fields.len() {
+            if i > 0 {
+                name = format!("{}.{}", name, fields[i - 1]);
+            }
+            if let Some(resolved) = self.candidate_names(&name).into_iter().find(|c| self.is_declared(c)) {
+                longest = (resolved, i);
+            }
+        }
+        longest
+    }
+    /// The variable with exactly this name, as returned by `resolve_qualified_variable`.
+    pub(crate) fn variable(&self, resolved_name: &str) -> Option<&Val> {
+        self.local(resolved_name).or_else(|| self.root_variable(resolved_name))
+    }
+    fn is_declared(&self, name: &str) -> bool {
+        self.root().declarations.contains_key(name) || self.root_variable(name).is_some()
+    }
+
+    /// Sets the container, such as `com.acme.policy`, that names are resolved in.
+    /// A name `x` then refers to the first of `com.acme.policy.x`, `com.acme.x`,
+    /// `com.x` and `x` that exists. A leading dot, as in `.x`, skips the container.
+    pub fn container(&mut self, container: &str) -> &mut Self {
+        self.container = container.to_string();
+        self
+    }
+    /// Makes `alias` stand for the qualified name, so that with the alias `Msg` for
+    /// `com.acme.v1.Msg`, `Msg.Field` refers to `com.acme.v1.Msg.Field`.
+    pub fn add_alias(&mut self, alias: &str, qualified_name: &str) -> &mut Self {
+        self.aliases.insert(alias.to_string(), qualified_name.to_string());
+        self
+    }
+    /// Sets the provider of the types that construction expressions such as
+    /// `pkg.Msg{field: value}` build. It is consulted before the types of
+    /// descriptor sets.
+    pub fn set_type_provider(&mut self, provider: impl TypeProvider + 'static) -> &mut Self {
+        self.custom_type_provider = Some(Rc::new(provider));
+        self.update_type_provider();
+        self
+    }
+    /// Registers the messages and enums of an encoded `FileDescriptorSet`, such as
+    /// `protoc --descriptor_set_out` writes, along with the well-known types.
+    /// Messages can then be built and their fields checked, and enum values such as
+    /// `pkg.Color.RED` read as ints. Variables take precedence over enum values of
+    /// the same name.
+    pub fn add_file_descriptor_set(&mut self, bytes: &[u8]) -> Result<&mut Self, DescriptorError> {
+        let mut pool = self.descriptors.clone().unwrap_or_else(well_known_types);
+        pool.decode_file_descriptor_set(bytes)?;
+        self.enum_constants = pool
+            .all_enums()
+            .flat_map(|e| {
+                e.values()
+                    .map(|value| (format!("{}.{}", e.full_name(), value.name()), Val::new_int(value.number() as i64)))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        self.descriptors = Some(pool);
+        self.update_type_provider();
+        Ok(self)
+    }
+    fn update_type_provider(&mut self) {
+        let proto = self
+            .descriptors
+            .clone()
+            .map(|pool| Rc::new(ProtoTypeProvider::new(pool)) as Rc<dyn TypeProvider>);
+        self.type_provider = match (self.custom_type_provider.clone(), proto) {
+            (Some(custom), Some(proto)) => Some(Rc::new(TypeProviders(vec![custom, proto]))),
+            (custom, proto) => custom.or(proto),
+        };
+    }
+    /// Reads a `FileDescriptorSet` from a file, such as a `.binpb` file, and
+    /// registers it as [`Context::add_file_descriptor_set`] does.
+    pub fn add_file_descriptor_set_file(&mut self, path: impl AsRef<Path>) -> std::io::Result<&mut Self> {
+        let bytes = std::fs::read(path)?;
+        self.add_file_descriptor_set(&bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+    pub(crate) fn type_provider(&self) -> Option<&dyn TypeProvider> {
+        self.root().type_provider.as_deref()
+    }
+    /// The full name of the type a name refers to, resolved against the container.
+    pub(crate) fn resolve_type(&self, name: &str) -> Option<String> {
+        let provider = self.type_provider()?;
+        self.candidate_names(name).into_iter().find(|c| provider.has_type(c))
+    }
+    /// Names a reference may resolve to, most qualified first.
+    pub(crate) fn candidate_names(&self, name: &str) -> Vec<String> {
+        if let Some(rooted) = name.strip_prefix('.') {
+            return vec![rooted.to_string()];
+        }
+        let root = self.root();
+        let (first, rest) = match name.split_once('.') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (name, None),
+        };
+        if let Some(qualified) = root.aliases.get(first) {
+            return vec![match rest {
+                Some(rest) => format!("{}.{}", qualified, rest),
+                None => qualified.clone(),
+            }];
+        }
+
+        let mut candidates = Vec::new();
+        let mut prefix = root.container.as_str();
+        while !prefix.is_empty() {
+            candidates.push(format!("{}.{}", prefix, name));
+            prefix = prefix.rsplit_once('.').map_or("", |(p, _)| p);
+        }
+        candidates.push(name.to_string());
+        candidates
+    }
+
+    /// Marks the attributes matching the pattern as unknown, so expressions depending
+    /// on them evaluate to an unknown value rather than reading the variable.
+    pub fn add_attribute_pattern(&mut self, pattern: AttributePattern) -> &mut Self {
+        self.unknowns.push(pattern);
+        self
+    }
+    pub(crate) fn is_unknown(&self, variable: &str, qualifiers: &[Val]) -> bool {
+        self.local(variable).is_none() && self.root().unknowns.iter().any(|p| p.matches(variable, qualifiers))
     }
 
+    /// Declares the type of a variable whose value is only bound at evaluation time.
+    pub fn declare_variable(&mut self, name: &'static str, ty: Type) -> &mut Self {
+        self.declarations.insert(name, ty);
+        self
+    }
+    pub(crate) fn resolve_variable_type(&self, name: &str) -> Option<Type> {
+        let root = self.root();
+        self.candidate_names(name).iter().find_map(|name| {
+            root.declarations
+                .get(name.as_str())
+                .cloned()
+                .or_else(|| self.root_variable(name).map(type_of))
+        })
+    }
+    pub(crate) fn variable_names(&self) -> impl Iterator<Item = &str> {
+        let root = self.root();
+        root.declarations.keys().copied().chain(root.variables.keys().map(String::as_str))
+    }
+
+    /// Registers a function. A function added under a name that is already taken
+    /// is tried first, and the earlier ones are still called for arguments none
+    /// of its overloads accept.
     pub fn add_function(&mut self, name: &'static str, func: Function) -> &mut Self {
-        self.funtions.insert(name, func);
+        self.funtions.entry(name).or_default().push(func);
+        self
+    }
+    /// The function most recently added under the name.
+    pub fn resolve_function(&self, name: &str) -> Option<&Function> {
+        self.resolve_functions(name)?.last()
+    }
+    /// Every function added under the name, oldest first.
+    pub(crate) fn resolve_functions(&self, name: &str) -> Option<&[Function]> {
+        let root = self.root();
+        self.candidate_names(name)
+            .iter()
+            .find_map(|c| root.funtions.get(c.as_str()))
+            .map(Vec::as_slice)
+    }
+    /// The namespaced function a call such as `strings.quote(s)` refers to. When no
+    /// such function exists, the call is a receiver call like `name.size()`. A
+    /// variable whose name is the namespace or a prefix of it takes precedence.
+    pub(crate) fn namespaced_function(&self, operand: &Expression, name: &str) -> Option<String> {
+        let namespace = operand.qualified_name()?;
+        if self.local(namespace.split('.').next().unwrap_or_default()).is_some() {
+            return None;
+        }
+        let variable = namespace
+            .match_indices('.')
+            .map(|(i, _)| &namespace[..i])
+            .chain([namespace.as_str()])
+            .any(|prefix| self.candidate_names(prefix).iter().any(|c| self.is_declared(c)));
+        if variable {
+            return None;
+        }
+        let function = format!("{}.{}", namespace, name);
+        self.resolve_function(&function).is_some().then_some(function)
+    }
+
+    /// Adds every function of the library.
+    pub fn add_library(&mut self, library: impl Library) -> &mut Self {
+        for func in library.functions() {
+            self.add_function(func.name, func);
+        }
+        self
+    }
+
+    pub(crate) fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.root().funtions.keys().copied()
+    }
+
+    /// Makes the checker reject list and map literals whose members have mixed types,
+    /// such as `[1, "a"]`. Members wrapped in `dyn(...)` are still accepted.
+    pub fn homogeneous_aggregate_literals(&mut self, enabled: bool) -> &mut Self {
+        self.homogeneous_aggregate_literals = enabled;
         self
     }
-    pub fn resolve_function(&self, name: &String) -> Option<&Function> {
-        self.funtions.get(name.as_str())
+    pub(crate) fn homogeneous_aggregate_literals_enabled(&self) -> bool {
+        self.root().homogeneous_aggregate_literals
     }
 
-    pub fn parent(&self) -> Option<Rc<Context>> {
-        self.par.clone()
+    /// The context this local scope was opened in.
+    pub fn parent(&self) -> Option<&Context> {
+        self.par.as_deref()
+    }
+}
+
+/// A local scope that puts its parent back in place when dropped, even if
+/// evaluation unwinds.
+struct Scope<'a> {
+    scope: Context,
+    context: &'a mut Context,
+}
+
+impl Drop for Scope<'_> {
+    fn drop(&mut self) {
+        let par = self.scope.par.take().expect("a scope has a parent");
+        *self.context = Rc::try_unwrap(par).ok().expect("a scope is the only owner of its parent");
+    }
+}
+
+/// A pool of only the well-known types, which descriptor sets import without
+/// always including them.
+fn well_known_types() -> DescriptorPool {
+    let files = DescriptorPool::global()
+        .files()
+        .filter(|f| f.package_name() == "google.protobuf")
+        .map(|f| f.file_descriptor_proto().clone())
+        .collect::<Vec<_>>();
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_protos(files).expect("the well-known types are valid");
+    pool
+}
+
+/// The name without the leading dot that makes it resolve from the root scope.
+fn root_scoped(name: &str) -> &str {
+    name.strip_prefix('.').unwrap_or(name)
+}
+
+/// The checker type of a bound value, naming the message type of messages.
+fn type_of(val: &Val) -> Type {
+    match val.as_message() {
+        Some(m) => Type::Message(m.descriptor().full_name().to_string()),
+        None => Type::from(&val.ty()),
     }
 }
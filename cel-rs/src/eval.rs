@@ -1,19 +1,37 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use crate::parser::{Atom, Expression, Member, RelationOp};
-use crate::value::value::Val;
+use crate::function::dispatch;
+use crate::parser::{ArithmeticOp, Atom, Comprehension, ExprId, ExprKind, Expression, Member, RelationOp, UnaryOp};
+use crate::value::duration::format_duration;
+use crate::value::error::Error;
+use crate::value::timestamp::format_timestamp;
+use crate::value::ty::Ty;
+use crate::value::unknown::propagate;
+use crate::value::value::{Val, Value};
 use crate::Context;
 
 #[derive(Default)]
-pub struct Eval {}
+pub struct Eval {
+    /// Value of every evaluated expression, recorded when a residual is wanted.
+    state: Option<RefCell<HashMap<ExprId, Val>>>,
+}
 
 impl Eval {
+    /// An evaluator that remembers what each expression evaluated to.
+    pub fn tracking() -> Self {
+        Self {
+            state: Some(Default::default()),
+        }
+    }
+
     fn eval_function(
         &self,
-        name: Rc<String>,
+        name: &str,
         receiver: Option<Val>,
-        argexprs: Vec<Expression>,
+        argexprs: &[Expression],
         ctx: &mut Context,
     ) -> Val {
         let mut args = Vec::with_capacity(argexprs.len() + 1);
@@ -26,88 +44,308 @@ impl Eval {
             args.push(self.eval(expr, ctx));
         }
 
-        if let Some(func) = ctx.resolve_function(&name) {
-            return (func.overloads.first().unwrap().func)(args)
+        if let Some(functions) = ctx.resolve_functions(name) {
+            return dispatch(functions, args);
         }
-        Val::new_error("unknown func".to_string())
+        Error::unknown_function(name)
     }
-    fn eval_member(&self, expr: Box<Expression>, member: Box<Member>, ctx: &mut Context) -> Val {
-        let v = self.eval(*expr, ctx);
-        match *member {
-            crate::parser::Member::Attribute(attr) => todo!(),
-            crate::parser::Member::FunctionCall(name, argexprs) => {
+    fn eval_member(&self, expr: &Expression, operand: &Expression, member: &Member, ctx: &mut Context) -> Val {
+        match member {
+            Member::Attribute(_) | Member::Index(_) => self.eval_attribute(expr, ctx),
+            Member::FunctionCall(name, argexprs) => {
+                if let Some(function) = ctx.namespaced_function(operand, name) {
+                    return self.eval_function(&function, None, argexprs, ctx);
+                }
+                let v = self.eval(operand, ctx);
                 self.eval_function(name, Some(v), argexprs, ctx)
             }
-            crate::parser::Member::Index(i) => todo!(),
-            crate::parser::Member::Fields(_) => todo!(),
+            Member::Fields(fields) => self.eval_construction(operand, fields, ctx),
         }
     }
 
-    pub fn eval(&self, expr: Expression, ctx: &mut Context) -> Val {
-        match expr {
-            Expression::GlobalFunctionCall(name, argexprs) => {
+    /// Evaluates `pkg.Msg{field: value}` by having the context's type provider build
+    /// the message.
+    fn eval_construction(&self, operand: &Expression, fields: &[(Rc<String>, Expression)], ctx: &mut Context) -> Val {
+        let name = operand.qualified_name().unwrap_or_default();
+        let Some(type_name) = ctx.resolve_type(&name) else {
+            return Error::unknown_type(&name);
+        };
+        let values: Vec<(&str, Val)> = fields.iter().map(|(f, e)| (f.as_str(), self.eval(e, ctx))).collect();
+        if let Some(v) = propagate(values.iter().map(|(_, v)| v)) {
+            return v;
+        }
+        ctx.type_provider().unwrap().new_value(&type_name, values)
+    }
+
+    /// Evaluates a variable followed by field selections and indexes as a single
+    /// attribute, so that it can be matched against the context's unknown patterns.
+    fn eval_attribute(&self, expr: &Expression, ctx: &mut Context) -> Val {
+        let mut steps = Vec::new();
+        let mut root = expr;
+        while let ExprKind::Member(operand, member) = &root.kind {
+            if !matches!(member.as_ref(), Member::Attribute(_) | Member::Index(_)) {
+                break;
+            }
+            steps.push(member.as_ref());
+            root = operand;
+        }
+        steps.reverse();
+
+        let variable = match &root.kind {
+            ExprKind::Ident(name) => {
+                let fields: Vec<&str> = steps
+                    .iter()
+                    .map_while(|step| match step {
+                        Member::Attribute(field) => Some(field.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                let (variable, consumed) = ctx.resolve_qualified_variable(name, &fields);
+                steps.drain(..consumed);
+                Some(variable)
+            }
+            _ => None,
+        };
+        let base = match variable {
+            Some(_) => None,
+            None => Some(self.eval(root, ctx)),
+        };
+
+        let mut qualifiers = Vec::with_capacity(steps.len());
+        for step in steps.iter() {
+            qualifiers.push(match step {
+                Member::Index(index) => self.eval(index, ctx),
+                Member::Attribute(field) => Val::new_string(field),
+                _ => unreachable!(),
+            });
+        }
+        if let Some(v) = propagate(base.iter().chain(qualifiers.iter())) {
+            return v;
+        }
+
+        let mut val = match (variable, base) {
+            (Some(name), _) if ctx.is_unknown(&name, &qualifiers) => return Val::new_unknown(expr.id),
+            (Some(name), _) => match ctx.variable(&name) {
+                Some(v) => v.clone(),
+                None => return Error::unknown_variable(&name),
+            },
+            (None, base) => base.unwrap(),
+        };
+        for (step, qualifier) in steps.iter().zip(qualifiers.iter()) {
+            if let Some(v) = propagate([&val]) {
+                return v;
+            }
+            val = match step {
+                Member::Attribute(field) => val.select(field),
+                _ => val.index(qualifier),
+            };
+        }
+        val
+    }
+
+    pub fn eval(&self, expr: &Expression, ctx: &mut Context) -> Val {
+        let mut val = self.eval_expr(expr, ctx);
+        if let Some(err) = val.as_error().filter(|e| e.id().is_none()) {
+            val = err.at(expr.id, expr.span);
+        }
+        if let Some(state) = &self.state {
+            state.borrow_mut().insert(expr.id, val.clone());
+        }
+        val
+    }
+
+    fn eval_expr(&self, expr: &Expression, ctx: &mut Context) -> Val {
+        match &expr.kind {
+            ExprKind::GlobalFunctionCall(name, argexprs) => {
                 self.eval_function(name, None, argexprs, ctx)
             }
-            Expression::Arithmetic(_, _, _) => todo!(),
-            Expression::Relation(left, op, right) => {
-                let l = self.eval(*left, ctx);
-                let r = self.eval(*right, ctx);
-                Val::new_bool(match op {
-                    RelationOp::Equals => l.eq(&r),
-                    RelationOp::LessThan => l.lt(&r),
-                    RelationOp::LessThanEq => l.le(&r),
-                    RelationOp::GreaterThan => l.gt(&r),
-                    RelationOp::GreaterThanEq => l.ge(&r),
-                    RelationOp::NotEquals => l.ne(&r),
-                    RelationOp::In => todo!("in"),
-                })
+            ExprKind::Arithmetic(left, op, _, right) => {
+                let l = self.eval(left, ctx);
+                let r = self.eval(right, ctx);
+                if let Some(v) = propagate([&l, &r]) {
+                    return v;
+                }
+                match op {
+                    ArithmeticOp::Add => l.add(&r),
+                    ArithmeticOp::Subtract => l.subtract(&r),
+                    ArithmeticOp::Multiply => l.multiply(&r),
+                    ArithmeticOp::Divide => l.divide(&r),
+                    ArithmeticOp::Modulus => l.modulo(&r),
+                }
+            }
+            ExprKind::Relation(left, op, _, right) => {
+                let l = self.eval(left, ctx);
+                let r = self.eval(right, ctx);
+                if let Some(v) = propagate([&l, &r]) {
+                    return v;
+                }
+                match op {
+                    RelationOp::Equals => Val::new_bool(l.eq(&r)),
+                    RelationOp::NotEquals => Val::new_bool(l.ne(&r)),
+                    RelationOp::LessThan => compare(&l, &r, "_<_", Ordering::is_lt),
+                    RelationOp::LessThanEq => compare(&l, &r, "_<=_", Ordering::is_le),
+                    RelationOp::GreaterThan => compare(&l, &r, "_>_", Ordering::is_gt),
+                    RelationOp::GreaterThanEq => compare(&l, &r, "_>=_", Ordering::is_ge),
+                    RelationOp::In => r.contains(&l),
+                }
+            }
+            ExprKind::Ternary(condition, left, right) => {
+                let c = self.eval(condition, ctx);
+                match c.as_bool() {
+                    Some(true) => self.eval(left, ctx),
+                    Some(false) => self.eval(right, ctx),
+                    None => {
+                        if self.state.is_some() {
+                            // Either branch may end up in the residual; fold both.
+                            self.eval(left, ctx);
+                            self.eval(right, ctx);
+                        }
+                        propagate([&c]).unwrap_or_else(|| Error::no_matching_overload("_?_:_"))
+                    }
+                }
+            }
+            ExprKind::Or(left, right) => self.eval_logic(left, right, true, ctx),
+            ExprKind::And(left, right) => self.eval_logic(left, right, false, ctx),
+            ExprKind::Unary(op, operand) => {
+                let v = self.eval(operand, ctx);
+                if let Some(v) = propagate([&v]) {
+                    return v;
+                }
+                match op {
+                    UnaryOp::Not => match v.as_bool() {
+                        Some(b) => Val::new_bool(!b),
+                        None => Error::no_matching_overload("!_"),
+                    },
+                    UnaryOp::DoubleNot => match v.ty() {
+                        Ty::Bool => v,
+                        _ => Error::no_matching_overload("!_"),
+                    },
+                    UnaryOp::Minus => v.negate(),
+                    UnaryOp::DoubleMinus => {
+                        let negated = v.negate();
+                        propagate([&negated]).unwrap_or_else(|| negated.negate())
+                    }
+                }
+            }
+            ExprKind::Member(operand, member) => self.eval_member(expr, operand, member, ctx),
+            ExprKind::List(values) => self.eval_list(values, ctx),
+            ExprKind::Map(entries) => self.eval_map(entries, ctx),
+            ExprKind::Atom(atom) => self.eval_atom(atom, ctx),
+            ExprKind::Ident(_) => self.eval_attribute(expr, ctx),
+            ExprKind::Has(operand, field) => {
+                let v = self.eval(operand, ctx);
+                propagate([&v]).unwrap_or_else(|| v.has_field(field))
+            }
+            ExprKind::Comprehension(c) => self.eval_comprehension(c, ctx),
+        }
+    }
+
+    fn eval_comprehension(&self, c: &Comprehension, ctx: &mut Context) -> Val {
+        let range = self.eval(&c.iter_range, ctx);
+        if let Some(v) = propagate([&range]) {
+            return v;
+        }
+        // Lists iterate (index, element) and maps (key, value).
+        let entries: Vec<(Val, Val)> = match (range.as_list(), range.as_map()) {
+            (Some(list), _) => list.iter().enumerate().map(|(i, e)| (Val::new_int(i as i64), e.clone())).collect(),
+            (_, Some(map)) => map.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            _ => {
+                return Error::invalid_argument(format!(
+                    "expression of type '{}' cannot be the range of a comprehension",
+                    range.ty().to_string()
+                ))
+            }
+        };
+        let mut accu = self.eval(&c.accu_init, ctx);
+        ctx.scoped(|ctx| {
+            for (first, second) in entries {
+                match &c.iter_var2 {
+                    Some(var2) => {
+                        ctx.bind(&c.iter_var, first);
+                        ctx.bind(var2, second);
+                    }
+                    None if range.ty() == Ty::List => ctx.bind(&c.iter_var, second),
+                    None => ctx.bind(&c.iter_var, first),
+                }
+                ctx.bind(&c.accu_var, accu.clone());
+                if self.eval(&c.loop_condition, ctx).as_bool() == Some(&false) {
+                    break;
+                }
+                accu = self.eval(&c.loop_step, ctx);
             }
-            Expression::Ternary(_, _, _) => todo!(),
-            Expression::Or(_, _) => todo!(),
-            Expression::And(_, _) => todo!(),
-            Expression::Unary(_, _) => todo!(),
-            Expression::Member(expr, member) => self.eval_member(expr, member, ctx),
-            Expression::List(values) => self.eval_list(values, ctx),
-            Expression::Map(entries) => self.eval_map(entries, ctx),
-            Expression::Atom(atom) => self.eval_atom(atom, ctx),
-            Expression::Ident(ident) => ctx
-                .resolve_variable(&ident)
-                .unwrap_or(&Val::new_error(format!("unknown variable {}", ident)))
-                .to_owned(),
-        }
-    }
-
-    fn eval_map(&self, entries: Vec<(Expression, Expression)>, ctx: &mut Context) -> Val {
+            ctx.bind(&c.accu_var, accu);
+            self.eval(&c.result, ctx)
+        })
+    }
+
+    /// `||` when `decisive` is true, `&&` otherwise. Either operand being decisive
+    /// settles the result even if the other is an error or unknown.
+    fn eval_logic(&self, left: &Expression, right: &Expression, decisive: bool, ctx: &mut Context) -> Val {
+        let l = self.eval(left, ctx);
+        if l.as_bool() == Some(&decisive) {
+            return l;
+        }
+        let r = self.eval(right, ctx);
+        if r.as_bool() == Some(&decisive) || (l.ty() == Ty::Bool && r.ty() == Ty::Bool) {
+            return r;
+        }
+        propagate([&l, &r]).unwrap_or_else(|| Error::no_matching_overload(if decisive { "_||_" } else { "_&&_" }))
+    }
+
+    fn eval_map(&self, entries: &[(Expression, Expression)], ctx: &mut Context) -> Val {
+        let entries: Vec<(Val, Val)> = entries
+            .iter()
+            .map(|(kexpr, vexpr)| (self.eval(kexpr, ctx), self.eval(vexpr, ctx)))
+            .collect();
+        if let Some(v) = propagate(entries.iter().flat_map(|(k, v)| [k, v])) {
+            return v;
+        }
+        // Keys that are equal, such as `1` and `1u`, are the same key.
         let mut map = HashMap::with_capacity(entries.len());
-        for (kexpr, vexpr) in entries {
-            let k = self.eval(kexpr, ctx);
-            let v = self.eval(vexpr, ctx);
+        for (k, v) in entries {
+            if map.contains_key(&k) {
+                return Error::invalid_argument("Failed with repeated key".to_string());
+            }
             map.insert(k, v);
         }
         Val::new_map(Rc::new(map))
     }
 
-    fn eval_list(&self, elems: Vec<Expression>, ctx: &mut Context) -> Val {
+    fn eval_list(&self, elems: &[Expression], ctx: &mut Context) -> Val {
         let mut list = Vec::with_capacity(elems.len());
         for expr in elems {
             let v = self.eval(expr, ctx);
             list.push(v);
         }
+        if let Some(v) = propagate(list.iter()) {
+            return v;
+        }
         Val::new_list(Rc::new(list))
     }
 
-    pub fn eval_atom(&self, atom: Atom, ctx: &mut Context) -> Val {
+    pub fn eval_atom(&self, atom: &Atom, _ctx: &mut Context) -> Val {
         match atom {
-            Atom::Int(i) => Val::new_int(i),
-            Atom::UInt(u) => Val::new_uint(u),
-            Atom::Float(f) => Val::new_double(f),
+            Atom::Int(i) => Val::new_int(*i),
+            Atom::UInt(u) => Val::new_uint(*u),
+            Atom::Float(f) => Val::new_double(*f),
             Atom::String(s) => Val::new_string(s),
-            Atom::Bytes(b) => Val::new_bytes(b),
-            Atom::Bool(b) => Val::new_bool(b),
+            Atom::Bytes(b) => Val::new_bytes(b.clone()),
+            Atom::Bool(b) => Val::new_bool(*b),
             Atom::Null => Val::new_null(),
         }
     }
 
+    /// Rebuilds `expr` with every subexpression whose value is known replaced by
+    /// that value, leaving only what still depends on unknown attributes.
+    /// An evaluator that isn't tracking has folded nothing, so its residual is the
+    /// expression itself.
+    pub fn residual(&self, expr: &Expression) -> Expression {
+        match &self.state {
+            Some(state) => prune(expr, &state.borrow()),
+            None => expr.clone(),
+        }
+    }
+
     // pub fn eval(&self, expr: Expression, ctx: &mut Context) -> impl Bag {
     //     match expr {
     //         Expression::Atom(atom) => Value::from(atom),
@@ -154,3 +392,75 @@ impl Eval {
     //     }
     // }
 }
+
+fn compare(l: &Val, r: &Val, function: &str, test: fn(Ordering) -> bool) -> Val {
+    match l.compare(r) {
+        Some(o) => Val::new_bool(test(o.into())),
+        None => Error::no_matching_overload(function),
+    }
+}
+
+fn prune(expr: &Expression, state: &HashMap<ExprId, Val>) -> Expression {
+    if let Some(kind) = state.get(&expr.id).and_then(literal) {
+        return Expression { kind, ..expr.clone() };
+    }
+    let known_bool = |e: &Expression| state.get(&e.id).and_then(|v| v.as_bool().copied());
+    match &expr.kind {
+        // `true && x` and `false || x` are just `x`.
+        ExprKind::And(left, right) | ExprKind::Or(left, right) => {
+            let identity = matches!(expr.kind, ExprKind::And(..));
+            if known_bool(left) == Some(identity) {
+                return prune(right, state);
+            }
+            if known_bool(right) == Some(identity) {
+                return prune(left, state);
+            }
+        }
+        ExprKind::Ternary(condition, left, right) => match known_bool(condition) {
+            Some(true) => return prune(left, state),
+            Some(false) => return prune(right, state),
+            None => {}
+        },
+        // The loop body's values are only those of the last iteration.
+        ExprKind::Comprehension(_) => return expr.clone(),
+        _ => {}
+    }
+    let mut pruned = expr.clone();
+    for child in pruned.children_mut() {
+        *child = prune(child, state);
+    }
+    pruned
+}
+
+/// A call such as `timestamp("2024-01-01T00:00:00Z")`.
+fn conversion(function: &str, arg: String) -> ExprKind {
+    let arg = Expression::new(0, 0, ExprKind::Atom(Atom::String(Rc::new(arg))));
+    ExprKind::GlobalFunctionCall(Rc::new(function.to_string()), vec![arg])
+}
+
+/// Literal expression for a value, if it has one.
+fn literal(val: &Val) -> Option<ExprKind> {
+    let atom = match val.ty() {
+        Ty::Int => Atom::Int(*val.as_int()?),
+        Ty::UInt => Atom::UInt(*val.as_uint()?),
+        Ty::Double => Atom::Float(val.as_double().copied().filter(|d| d.is_finite())?),
+        Ty::String => Atom::String(Rc::new(val.as_string()?.to_string())),
+        Ty::Bytes => Atom::Bytes(Rc::new(val.as_bytes()?.to_vec())),
+        Ty::Bool => Atom::Bool(*val.as_bool()?),
+        Ty::Null => Atom::Null,
+        Ty::Timestamp => return Some(conversion("timestamp", format_timestamp(val.as_timestamp()?))),
+        Ty::Duration => return Some(conversion("duration", format_duration(val.as_duration()?))),
+        Ty::List => {
+            let elems = val.as_list()?.iter().map(|v| literal(v).map(|k| Expression::new(0, 0, k)));
+            return elems.collect::<Option<Vec<_>>>().map(ExprKind::List);
+        }
+        Ty::Map => {
+            let entries = val.as_map()?.iter().map(|(k, v)| {
+                Some((Expression::new(0, 0, literal(k)?), Expression::new(0, 0, literal(v)?)))
+            });
+            return entries.collect::<Option<Vec<_>>>().map(ExprKind::Map);
+        }
+        _ => return None,
+    };
+    Some(ExprKind::Atom(atom))
+}
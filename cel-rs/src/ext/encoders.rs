@@ -0,0 +1,50 @@
+use base64::{
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::{Error, ErrorCode}, ty::Ty},
+    Val,
+};
+
+/// Base64 encoding and decoding, matching the cel-go encoders extension.
+pub struct Encoders;
+
+impl Library for Encoders {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "base64.encode",
+                overloads: &[
+                    Overload { key: "base64_encode_bytes", args: &[Ty::Bytes], result: Ty::String, func: encode, strict: true },
+                ],
+            },
+            Function {
+                name: "base64.decode",
+                overloads: &[
+                    Overload { key: "base64_decode_string", args: &[Ty::String], result: Ty::Bytes, func: decode, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+/// The standard alphabet, padding output and accepting input with or without it.
+const STANDARD: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+fn encode(args: Vec<Val>) -> Val {
+    Val::new_string(STANDARD.encode(args[0].as_bytes().unwrap()))
+}
+
+fn decode(args: Vec<Val>) -> Val {
+    match STANDARD.decode(args[0].as_string().unwrap()) {
+        Ok(bytes) => Val::new_bytes(bytes.into()),
+        Err(e) => Error::with_source(ErrorCode::InvalidArgument, e),
+    }
+}
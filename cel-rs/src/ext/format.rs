@@ -0,0 +1,234 @@
+//! `string.format(list)`, with printf-style clauses.
+
+use super::strings::quoted;
+use crate::{
+    value::{duration::format_duration, error::Error, timestamp::format_timestamp, ty::Ty},
+    Val, Value,
+};
+
+/// The most digits a double can have after the decimal point (the smallest
+/// subnormal is 2^-1074); anything beyond is padding.
+const MAX_PRECISION: usize = 1074;
+
+pub(super) fn format(args: Vec<Val>) -> Val {
+    let (template, values) = (args[0].as_string().unwrap(), args[1].as_list().unwrap());
+    let mut out = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue;
+        }
+
+        let mut precision = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut digits = String::new();
+            while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(*d);
+                chars.next();
+            }
+            if digits.is_empty() {
+                return clause_error("missing precision");
+            }
+            precision = match digits.parse::<usize>() {
+                Ok(p) if p <= MAX_PRECISION => Some(p),
+                _ => return clause_error(&format!("precision exceeds {}", MAX_PRECISION)),
+            };
+        }
+        let Some(verb) = chars.next() else {
+            return clause_error("unterminated formatting clause");
+        };
+        let Some(arg) = values.get(next) else {
+            return Error::invalid_argument(format!("index {} out of range", next));
+        };
+        next += 1;
+
+        let formatted = match verb {
+            's' => string_clause(arg),
+            'd' => decimal_clause(arg),
+            'f' => fixed_clause(arg, precision.unwrap_or(6)),
+            'e' => scientific_clause(arg, precision.unwrap_or(6)),
+            'x' => hex_clause(arg),
+            'X' => hex_clause(arg).map(|s| s.to_uppercase()),
+            'o' => octal_clause(arg),
+            'b' => binary_clause(arg),
+            verb => return clause_error(&format!("unrecognized formatting clause \"{}\"", verb)),
+        };
+        match formatted {
+            Ok(s) => out.push_str(&s),
+            Err(message) => return Error::invalid_argument(format!("error during formatting: {}", message)),
+        }
+    }
+
+    if next < values.len() {
+        return Error::invalid_argument(format!(
+            "too many arguments supplied to format: expected {}, got {}",
+            next,
+            values.len()
+        ));
+    }
+    Val::new_string(out)
+}
+
+fn clause_error(message: &str) -> Val {
+    Error::invalid_argument(format!("could not parse formatting clause: {}", message))
+}
+
+fn type_error(clause: &str, expected: &str, arg: &Val) -> String {
+    format!("{} clause can only be used on {}, was given {}", clause, expected, arg.ty().to_string())
+}
+
+fn string_clause(arg: &Val) -> Result<String, String> {
+    if let Some(s) = arg.as_string() {
+        return Ok(s.to_string());
+    }
+    if let Some(b) = arg.as_bytes() {
+        return Ok(String::from_utf8_lossy(b).into_owned());
+    }
+    if let Some(t) = arg.as_timestamp() {
+        return Ok(format_timestamp(t));
+    }
+    if let Some(d) = arg.as_duration() {
+        return Ok(format_duration(d));
+    }
+    literal(arg).ok_or_else(|| format!("string clause can not be used on {}", arg.ty().to_string()))
+}
+
+/// Formats a value the way it would be written in an expression, as members of
+/// lists and maps are.
+fn literal(arg: &Val) -> Option<String> {
+    Some(match arg.ty() {
+        Ty::Null => "null".to_string(),
+        Ty::Bool => arg.as_bool()?.to_string(),
+        Ty::Int => arg.as_int()?.to_string(),
+        Ty::UInt => arg.as_uint()?.to_string(),
+        Ty::Double => double(*arg.as_double()?),
+        Ty::String => quoted(arg.as_string()?),
+        Ty::Bytes => format!("b{}", quoted(&String::from_utf8_lossy(arg.as_bytes()?))),
+        Ty::Timestamp => format!("timestamp(\"{}\")", format_timestamp(arg.as_timestamp()?)),
+        Ty::Duration => format!("duration(\"{}\")", format_duration(arg.as_duration()?)),
+        Ty::List => {
+            let elems = arg.as_list()?.iter().map(literal).collect::<Option<Vec<_>>>()?;
+            format!("[{}]", elems.join(", "))
+        }
+        Ty::Map => {
+            let mut entries = arg
+                .as_map()?
+                .iter()
+                .map(|(k, v)| Some(format!("{}: {}", literal(k)?, literal(v)?)))
+                .collect::<Option<Vec<_>>>()?;
+            entries.sort();
+            format!("{{{}}}", entries.join(", "))
+        }
+        _ => return None,
+    })
+}
+
+fn double(d: f64) -> String {
+    match d {
+        d if d.is_nan() => "NaN".to_string(),
+        d if d.is_infinite() && d > 0.0 => "Infinity".to_string(),
+        d if d.is_infinite() => "-Infinity".to_string(),
+        d => d.to_string(),
+    }
+}
+
+fn decimal_clause(arg: &Val) -> Result<String, String> {
+    if let Some(i) = arg.as_int() {
+        return Ok(i.to_string());
+    }
+    if let Some(u) = arg.as_uint() {
+        return Ok(u.to_string());
+    }
+    Err(type_error("decimal", "integers", arg))
+}
+
+fn number(clause: &str, arg: &Val) -> Result<f64, String> {
+    if let Some(d) = arg.as_double() {
+        return Ok(*d);
+    }
+    if let Some(i) = arg.as_int() {
+        return Ok(*i as f64);
+    }
+    if let Some(u) = arg.as_uint() {
+        return Ok(*u as f64);
+    }
+    Err(type_error(clause, "integers and doubles", arg))
+}
+
+fn fixed_clause(arg: &Val, precision: usize) -> Result<String, String> {
+    let d = number("fixed-point", arg)?;
+    if !d.is_finite() {
+        return Ok(double(d));
+    }
+    Ok(format!("{:.*}", precision, d))
+}
+
+/// Scientific notation with at least two exponent digits, e.g. `1.052033e+03`.
+fn scientific_clause(arg: &Val, precision: usize) -> Result<String, String> {
+    let d = number("scientific", arg)?;
+    if !d.is_finite() {
+        return Ok(double(d));
+    }
+    let formatted = format!("{:.*e}", precision, d);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+    Ok(format!("{}e{}{:0>2}", mantissa, sign, digits))
+}
+
+fn hex_clause(arg: &Val) -> Result<String, String> {
+    if let Some(i) = arg.as_int() {
+        return Ok(match *i < 0 {
+            true => format!("-{:x}", i.unsigned_abs()),
+            false => format!("{:x}", i),
+        });
+    }
+    if let Some(u) = arg.as_uint() {
+        return Ok(format!("{:x}", u));
+    }
+    let bytes = arg
+        .as_string()
+        .map(str::as_bytes)
+        .or_else(|| arg.as_bytes())
+        .ok_or_else(|| type_error("hex", "integers, byte buffers, and strings", arg))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn octal_clause(arg: &Val) -> Result<String, String> {
+    if let Some(i) = arg.as_int() {
+        return Ok(match *i < 0 {
+            true => format!("-{:o}", i.unsigned_abs()),
+            false => format!("{:o}", i),
+        });
+    }
+    if let Some(u) = arg.as_uint() {
+        return Ok(format!("{:o}", u));
+    }
+    Err(type_error("octal", "integers", arg))
+}
+
+fn binary_clause(arg: &Val) -> Result<String, String> {
+    if let Some(i) = arg.as_int() {
+        return Ok(match *i < 0 {
+            true => format!("-{:b}", i.unsigned_abs()),
+            false => format!("{:b}", i),
+        });
+    }
+    if let Some(u) = arg.as_uint() {
+        return Ok(format!("{:b}", u));
+    }
+    if let Some(b) = arg.as_bool() {
+        return Ok(u8::from(*b).to_string());
+    }
+    Err(type_error("binary", "integers and bools", arg))
+}
@@ -0,0 +1,220 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::Error, ty::Ty},
+    Val, Value,
+};
+
+/// List functions matching the cel-go lists extension. `sortBy` is a macro that
+/// expands to a call of `@sortByAssociatedKeys`.
+pub struct Lists;
+
+impl Library for Lists {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "slice",
+                overloads: &[
+                    Overload { key: "list_slice", args: &[Ty::List, Ty::Int, Ty::Int], result: Ty::List, func: slice, strict: true },
+                ],
+            },
+            Function {
+                name: "flatten",
+                overloads: &[
+                    Overload { key: "list_flatten", args: &[Ty::List], result: Ty::List, func: flatten, strict: true },
+                    Overload { key: "list_flatten_int", args: &[Ty::List, Ty::Int], result: Ty::List, func: flatten, strict: true },
+                ],
+            },
+            Function {
+                name: "lists.range",
+                overloads: &[
+                    Overload { key: "lists_range", args: &[Ty::Int], result: Ty::List, func: range, strict: true },
+                ],
+            },
+            Function {
+                name: "distinct",
+                overloads: &[
+                    Overload { key: "list_distinct", args: &[Ty::List], result: Ty::List, func: distinct, strict: true },
+                ],
+            },
+            Function {
+                name: "reverse",
+                overloads: &[
+                    Overload { key: "list_reverse", args: &[Ty::List], result: Ty::List, func: reverse, strict: true },
+                ],
+            },
+            Function {
+                name: "sort",
+                overloads: &[
+                    Overload { key: "list_sort", args: &[Ty::List], result: Ty::List, func: sort, strict: true },
+                ],
+            },
+            Function {
+                name: "@sortByAssociatedKeys",
+                overloads: &[
+                    Overload { key: "list_sortByAssociatedKeys", args: &[Ty::List, Ty::List], result: Ty::List, func: sort_by_associated_keys, strict: true },
+                ],
+            },
+            Function {
+                name: "first",
+                overloads: &[
+                    Overload { key: "list_first", args: &[Ty::List], result: Ty::Optional, func: first, strict: true },
+                ],
+            },
+            Function {
+                name: "last",
+                overloads: &[
+                    Overload { key: "list_last", args: &[Ty::List], result: Ty::Optional, func: last, strict: true },
+                ],
+            },
+            Function {
+                name: "indexOf",
+                overloads: &[
+                    Overload { key: "list_indexOf", args: &[Ty::List, Ty::Dyn], result: Ty::Int, func: index_of, strict: true },
+                ],
+            },
+            Function {
+                name: "lastIndexOf",
+                overloads: &[
+                    Overload { key: "list_lastIndexOf", args: &[Ty::List, Ty::Dyn], result: Ty::Int, func: last_index_of, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+fn list(args: &[Val]) -> &[Val] {
+    args[0].as_list().unwrap()
+}
+
+fn new_list(elems: Vec<Val>) -> Val {
+    Val::new_list(Rc::new(elems))
+}
+
+fn slice(args: Vec<Val>) -> Val {
+    let l = list(&args);
+    let (start, end) = (*args[1].as_int().unwrap(), *args[2].as_int().unwrap());
+    if start < 0 || end < 0 {
+        return Error::invalid_argument(format!(
+            "cannot slice({}, {}), negative indexes not supported",
+            start, end
+        ));
+    }
+    if start > end {
+        return Error::invalid_argument(format!(
+            "cannot slice({}, {}), start index must be less than or equal to end index",
+            start, end
+        ));
+    }
+    if end as usize > l.len() {
+        return Error::invalid_argument(format!("cannot slice({}, {}), list is length {}", start, end, l.len()));
+    }
+    new_list(l[start as usize..end as usize].to_vec())
+}
+
+fn flatten(args: Vec<Val>) -> Val {
+    let depth = args.get(1).map_or(1, |d| *d.as_int().unwrap());
+    if depth < 0 {
+        return Error::invalid_argument("level must be non-negative".to_string());
+    }
+    fn flatten_into(elems: &[Val], depth: i64, out: &mut Vec<Val>) {
+        for elem in elems {
+            match elem.as_list() {
+                Some(inner) if depth > 0 => flatten_into(inner, depth - 1, out),
+                _ => out.push(elem.clone()),
+            }
+        }
+    }
+    let mut out = Vec::new();
+    flatten_into(list(&args), depth, &mut out);
+    new_list(out)
+}
+
+fn range(args: Vec<Val>) -> Val {
+    let n = *args[0].as_int().unwrap();
+    new_list((0..n.max(0)).map(Val::new_int).collect())
+}
+
+fn distinct(args: Vec<Val>) -> Val {
+    let mut out: Vec<Val> = Vec::new();
+    for elem in list(&args) {
+        if !out.contains(elem) {
+            out.push(elem.clone());
+        }
+    }
+    new_list(out)
+}
+
+fn reverse(args: Vec<Val>) -> Val {
+    new_list(list(&args).iter().rev().cloned().collect())
+}
+
+/// Checks that the values can be sorted: they must share a type with an ordering.
+fn check_sortable(values: &[Val]) -> Result<(), Val> {
+    let Some(first) = values.first() else {
+        return Ok(());
+    };
+    if values.iter().any(|v| v.ty() != first.ty()) {
+        return Err(Error::invalid_argument("list elements must have the same type".to_string()));
+    }
+    if first.compare(first).is_none() {
+        return Err(Error::invalid_argument(format!(
+            "list elements of type '{}' are not comparable",
+            first.ty().to_string()
+        )));
+    }
+    Ok(())
+}
+
+fn order(a: &Val, b: &Val) -> Ordering {
+    a.compare(b).map_or(Ordering::Equal, Into::into)
+}
+
+fn sort(args: Vec<Val>) -> Val {
+    let mut elems = list(&args).to_vec();
+    if let Err(e) = check_sortable(&elems) {
+        return e;
+    }
+    elems.sort_by(order);
+    new_list(elems)
+}
+
+/// Sorts the first list by the keys in the second, which `sortBy` computes for
+/// each element.
+fn sort_by_associated_keys(args: Vec<Val>) -> Val {
+    let (elems, keys) = (list(&args), args[1].as_list().unwrap());
+    if elems.len() != keys.len() {
+        return Error::invalid_argument(format!(
+            "@sortByAssociatedKeys() expected a list of the same size as the associated keys list, but got {} and {} elements respectively",
+            elems.len(),
+            keys.len()
+        ));
+    }
+    if let Err(e) = check_sortable(keys) {
+        return e;
+    }
+    let mut pairs: Vec<(&Val, &Val)> = keys.iter().zip(elems.iter()).collect();
+    pairs.sort_by(|a, b| order(a.0, b.0));
+    new_list(pairs.into_iter().map(|(_, e)| e.clone()).collect())
+}
+
+fn first(args: Vec<Val>) -> Val {
+    Val::new_optional(list(&args).first().cloned())
+}
+
+fn last(args: Vec<Val>) -> Val {
+    Val::new_optional(list(&args).last().cloned())
+}
+
+fn index_of(args: Vec<Val>) -> Val {
+    let i = list(&args).iter().position(|e| e == &args[1]);
+    Val::new_int(i.map_or(-1, |i| i as i64))
+}
+
+fn last_index_of(args: Vec<Val>) -> Val {
+    let i = list(&args).iter().rposition(|e| e == &args[1]);
+    Val::new_int(i.map_or(-1, |i| i as i64))
+}
@@ -0,0 +1,286 @@
+use std::cmp::Ordering;
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{double, error::Error, ty::Ty},
+    Val,
+};
+
+/// Numeric functions matching the cel-go math extension, called with the `math.`
+/// namespace, e.g. `math.greatest(a, b)`.
+pub struct Math;
+
+impl Library for Math {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function { name: "math.greatest", overloads: GREATEST },
+            Function { name: "math.least", overloads: LEAST },
+            Function {
+                name: "math.ceil",
+                overloads: &[Overload { key: "math_ceil_double", args: &[Ty::Double], result: Ty::Double, func: ceil, strict: true }],
+            },
+            Function {
+                name: "math.floor",
+                overloads: &[Overload { key: "math_floor_double", args: &[Ty::Double], result: Ty::Double, func: floor, strict: true }],
+            },
+            Function {
+                name: "math.round",
+                overloads: &[Overload { key: "math_round_double", args: &[Ty::Double], result: Ty::Double, func: round, strict: true }],
+            },
+            Function {
+                name: "math.trunc",
+                overloads: &[Overload { key: "math_trunc_double", args: &[Ty::Double], result: Ty::Double, func: trunc, strict: true }],
+            },
+            Function {
+                name: "math.abs",
+                overloads: &[
+                    Overload { key: "math_abs_int", args: &[Ty::Int], result: Ty::Int, func: abs, strict: true },
+                    Overload { key: "math_abs_uint", args: &[Ty::UInt], result: Ty::UInt, func: abs, strict: true },
+                    Overload { key: "math_abs_double", args: &[Ty::Double], result: Ty::Double, func: abs, strict: true },
+                ],
+            },
+            Function {
+                name: "math.sign",
+                overloads: &[
+                    Overload { key: "math_sign_int", args: &[Ty::Int], result: Ty::Int, func: sign, strict: true },
+                    Overload { key: "math_sign_uint", args: &[Ty::UInt], result: Ty::UInt, func: sign, strict: true },
+                    Overload { key: "math_sign_double", args: &[Ty::Double], result: Ty::Double, func: sign, strict: true },
+                ],
+            },
+            Function {
+                name: "math.isNaN",
+                overloads: &[Overload { key: "math_isNaN_double", args: &[Ty::Double], result: Ty::Bool, func: is_nan, strict: true }],
+            },
+            Function {
+                name: "math.isInf",
+                overloads: &[Overload { key: "math_isInf_double", args: &[Ty::Double], result: Ty::Bool, func: is_inf, strict: true }],
+            },
+            Function {
+                name: "math.isFinite",
+                overloads: &[Overload { key: "math_isFinite_double", args: &[Ty::Double], result: Ty::Bool, func: is_finite, strict: true }],
+            },
+            Function {
+                name: "math.sqrt",
+                overloads: &[
+                    Overload { key: "math_sqrt_int", args: &[Ty::Int], result: Ty::Double, func: sqrt, strict: true },
+                    Overload { key: "math_sqrt_uint", args: &[Ty::UInt], result: Ty::Double, func: sqrt, strict: true },
+                    Overload { key: "math_sqrt_double", args: &[Ty::Double], result: Ty::Double, func: sqrt, strict: true },
+                ],
+            },
+            Function {
+                name: "math.bitAnd",
+                overloads: &[
+                    Overload { key: "math_bitAnd_int_int", args: &[Ty::Int, Ty::Int], result: Ty::Int, func: bit_and, strict: true },
+                    Overload { key: "math_bitAnd_uint_uint", args: &[Ty::UInt, Ty::UInt], result: Ty::UInt, func: bit_and, strict: true },
+                ],
+            },
+            Function {
+                name: "math.bitOr",
+                overloads: &[
+                    Overload { key: "math_bitOr_int_int", args: &[Ty::Int, Ty::Int], result: Ty::Int, func: bit_or, strict: true },
+                    Overload { key: "math_bitOr_uint_uint", args: &[Ty::UInt, Ty::UInt], result: Ty::UInt, func: bit_or, strict: true },
+                ],
+            },
+            Function {
+                name: "math.bitXor",
+                overloads: &[
+                    Overload { key: "math_bitXor_int_int", args: &[Ty::Int, Ty::Int], result: Ty::Int, func: bit_xor, strict: true },
+                    Overload { key: "math_bitXor_uint_uint", args: &[Ty::UInt, Ty::UInt], result: Ty::UInt, func: bit_xor, strict: true },
+                ],
+            },
+            Function {
+                name: "math.bitNot",
+                overloads: &[
+                    Overload { key: "math_bitNot_int_int", args: &[Ty::Int], result: Ty::Int, func: bit_not, strict: true },
+                    Overload { key: "math_bitNot_uint_uint", args: &[Ty::UInt], result: Ty::UInt, func: bit_not, strict: true },
+                ],
+            },
+            Function {
+                name: "math.bitShiftLeft",
+                overloads: &[
+                    Overload { key: "math_bitShiftLeft_int_int", args: &[Ty::Int, Ty::Int], result: Ty::Int, func: bit_shift_left, strict: true },
+                    Overload { key: "math_bitShiftLeft_uint_int", args: &[Ty::UInt, Ty::Int], result: Ty::UInt, func: bit_shift_left, strict: true },
+                ],
+            },
+            Function {
+                name: "math.bitShiftRight",
+                overloads: &[
+                    Overload { key: "math_bitShiftRight_int_int", args: &[Ty::Int, Ty::Int], result: Ty::Int, func: bit_shift_right, strict: true },
+                    Overload { key: "math_bitShiftRight_uint_int", args: &[Ty::UInt, Ty::Int], result: Ty::UInt, func: bit_shift_right, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+// `math.greatest` and `math.least` take a single number, a list of numbers, or up
+// to six numbers of mixed types.
+
+const GREATEST: &[Overload] = &[
+    Overload { key: "math_@max_int", args: &[Ty::Int], result: Ty::Int, func: greatest, strict: true },
+    Overload { key: "math_@max_uint", args: &[Ty::UInt], result: Ty::UInt, func: greatest, strict: true },
+    Overload { key: "math_@max_double", args: &[Ty::Double], result: Ty::Double, func: greatest, strict: true },
+    Overload { key: "math_@max_list", args: &[Ty::List], result: Ty::Dyn, func: greatest, strict: true },
+    Overload { key: "math_@max_2", args: &[Ty::Dyn; 2], result: Ty::Dyn, func: greatest, strict: true },
+    Overload { key: "math_@max_3", args: &[Ty::Dyn; 3], result: Ty::Dyn, func: greatest, strict: true },
+    Overload { key: "math_@max_4", args: &[Ty::Dyn; 4], result: Ty::Dyn, func: greatest, strict: true },
+    Overload { key: "math_@max_5", args: &[Ty::Dyn; 5], result: Ty::Dyn, func: greatest, strict: true },
+    Overload { key: "math_@max_6", args: &[Ty::Dyn; 6], result: Ty::Dyn, func: greatest, strict: true },
+];
+
+const LEAST: &[Overload] = &[
+    Overload { key: "math_@min_int", args: &[Ty::Int], result: Ty::Int, func: least, strict: true },
+    Overload { key: "math_@min_uint", args: &[Ty::UInt], result: Ty::UInt, func: least, strict: true },
+    Overload { key: "math_@min_double", args: &[Ty::Double], result: Ty::Double, func: least, strict: true },
+    Overload { key: "math_@min_list", args: &[Ty::List], result: Ty::Dyn, func: least, strict: true },
+    Overload { key: "math_@min_2", args: &[Ty::Dyn; 2], result: Ty::Dyn, func: least, strict: true },
+    Overload { key: "math_@min_3", args: &[Ty::Dyn; 3], result: Ty::Dyn, func: least, strict: true },
+    Overload { key: "math_@min_4", args: &[Ty::Dyn; 4], result: Ty::Dyn, func: least, strict: true },
+    Overload { key: "math_@min_5", args: &[Ty::Dyn; 5], result: Ty::Dyn, func: least, strict: true },
+    Overload { key: "math_@min_6", args: &[Ty::Dyn; 6], result: Ty::Dyn, func: least, strict: true },
+];
+
+/// Orders numbers of any numeric type by value, or returns `None` if either is not a
+/// number or is NaN.
+fn compare_numbers(a: &Val, b: &Val) -> Option<Ordering> {
+    double::compare_numbers(a.integral(), a.as_number()?, b)
+}
+
+/// The argument ordered first by `wanted`, from either the arguments or the single
+/// list argument.
+fn extremum(function: &str, args: Vec<Val>, wanted: Ordering) -> Val {
+    let values = match args[0].as_list() {
+        Some(list) if args.len() == 1 => list.to_vec(),
+        _ => args,
+    };
+    let Some(mut best) = values.first().cloned() else {
+        return Error::invalid_argument(format!("{}(list) argument must not be empty", function));
+    };
+    for v in &values {
+        match compare_numbers(v, &best) {
+            Some(o) if o == wanted => best = v.clone(),
+            Some(_) => {}
+            None if v.as_number().is_some_and(f64::is_nan) => return v.clone(),
+            None if v.as_number().is_some() => {}
+            None => return Error::no_matching_overload(function),
+        }
+    }
+    best
+}
+
+fn greatest(args: Vec<Val>) -> Val {
+    extremum("math.greatest", args, Ordering::Greater)
+}
+
+fn least(args: Vec<Val>) -> Val {
+    extremum("math.least", args, Ordering::Less)
+}
+
+fn double(args: &[Val]) -> f64 {
+    *args[0].as_double().unwrap()
+}
+
+fn ceil(args: Vec<Val>) -> Val {
+    Val::new_double(double(&args).ceil())
+}
+
+fn floor(args: Vec<Val>) -> Val {
+    Val::new_double(double(&args).floor())
+}
+
+/// Rounds half away from zero.
+fn round(args: Vec<Val>) -> Val {
+    Val::new_double(double(&args).round())
+}
+
+fn trunc(args: Vec<Val>) -> Val {
+    Val::new_double(double(&args).trunc())
+}
+
+fn abs(args: Vec<Val>) -> Val {
+    if let Some(i) = args[0].as_int() {
+        return i.checked_abs().map_or_else(|| Error::overflow("integer"), Val::new_int);
+    }
+    if let Some(d) = args[0].as_double() {
+        return Val::new_double(d.abs());
+    }
+    args[0].clone()
+}
+
+fn sign(args: Vec<Val>) -> Val {
+    if let Some(i) = args[0].as_int() {
+        return Val::new_int(i.signum());
+    }
+    if let Some(u) = args[0].as_uint() {
+        return Val::new_uint(u64::from(*u != 0));
+    }
+    match double(&args) {
+        d if d.is_nan() || d == 0.0 => Val::new_double(d),
+        d => Val::new_double(d.signum()),
+    }
+}
+
+fn is_nan(args: Vec<Val>) -> Val {
+    Val::new_bool(double(&args).is_nan())
+}
+
+fn is_inf(args: Vec<Val>) -> Val {
+    Val::new_bool(double(&args).is_infinite())
+}
+
+fn is_finite(args: Vec<Val>) -> Val {
+    Val::new_bool(double(&args).is_finite())
+}
+
+fn sqrt(args: Vec<Val>) -> Val {
+    Val::new_double(args[0].as_number().unwrap().sqrt())
+}
+
+fn bitwise(args: &[Val], int: fn(i64, i64) -> i64, uint: fn(u64, u64) -> u64) -> Val {
+    match (args[0].as_int(), args[1].as_int()) {
+        (Some(a), Some(b)) => Val::new_int(int(*a, *b)),
+        _ => Val::new_uint(uint(*args[0].as_uint().unwrap(), *args[1].as_uint().unwrap())),
+    }
+}
+
+fn bit_and(args: Vec<Val>) -> Val {
+    bitwise(&args, |a, b| a & b, |a, b| a & b)
+}
+
+fn bit_or(args: Vec<Val>) -> Val {
+    bitwise(&args, |a, b| a | b, |a, b| a | b)
+}
+
+fn bit_xor(args: Vec<Val>) -> Val {
+    bitwise(&args, |a, b| a ^ b, |a, b| a ^ b)
+}
+
+fn bit_not(args: Vec<Val>) -> Val {
+    match args[0].as_int() {
+        Some(i) => Val::new_int(!i),
+        None => Val::new_uint(!args[0].as_uint().unwrap()),
+    }
+}
+
+/// Shifts the bits of an int or uint. Ints are shifted as unsigned, and shifting
+/// by 64 or more gives zero.
+fn shift(function: &str, args: &[Val], op: fn(u64, u32) -> Option<u64>) -> Val {
+    let offset = *args[1].as_int().unwrap();
+    if offset < 0 {
+        return Error::invalid_argument(format!("{}() negative offset: {}", function, offset));
+    }
+    let offset = u32::try_from(offset).unwrap_or(u32::MAX);
+    match args[0].as_int() {
+        Some(i) => Val::new_int(op(*i as u64, offset).unwrap_or(0) as i64),
+        None => Val::new_uint(op(*args[0].as_uint().unwrap(), offset).unwrap_or(0)),
+    }
+}
+
+fn bit_shift_left(args: Vec<Val>) -> Val {
+    shift("math.bitShiftLeft", &args, u64::checked_shl)
+}
+
+fn bit_shift_right(args: Vec<Val>) -> Val {
+    shift("math.bitShiftRight", &args, u64::checked_shr)
+}
@@ -0,0 +1,28 @@
+//! Optional function libraries, added to a context with [`Context::add_library`].
+//!
+//! [`Context::add_library`]: crate::Context::add_library
+
+mod encoders;
+mod format;
+mod lists;
+mod math;
+mod network;
+mod optionals;
+mod quantity;
+mod regex;
+mod semver;
+mod sets;
+mod strings;
+mod urls;
+
+pub use encoders::Encoders;
+pub use lists::Lists;
+pub use math::Math;
+pub use network::Network;
+pub use optionals::Optionals;
+pub use quantity::Quantities;
+pub use self::regex::Regex;
+pub use semver::Semver;
+pub use sets::Sets;
+pub use strings::Strings;
+pub use urls::Urls;
@@ -0,0 +1,308 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::Error, ty::Ty},
+    Val, Value,
+};
+
+const IP: Ty = Ty::Opaque("net.IP");
+const CIDR: Ty = Ty::Opaque("net.CIDR");
+
+/// The Kubernetes network library: `ip("10.0.0.1")` and `cidr("10.0.0.0/8")`
+/// values with their accessors. Addresses must be written as Kubernetes accepts
+/// them, without leading zeros, zones, or IPv4-mapped IPv6 forms.
+pub struct Network;
+
+impl Library for Network {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "ip",
+                overloads: &[
+                    Overload { key: "string_to_ip", args: &[Ty::String], result: IP, func: ip, strict: true },
+                    Overload { key: "cidr_ip", args: &[CIDR], result: IP, func: cidr_ip, strict: true },
+                ],
+            },
+            Function {
+                name: "isIP",
+                overloads: &[Overload { key: "is_ip", args: &[Ty::String], result: Ty::Bool, func: is_ip, strict: true }],
+            },
+            Function {
+                name: "ip.isCanonical",
+                overloads: &[Overload { key: "ip_is_canonical", args: &[Ty::String], result: Ty::Bool, func: is_canonical, strict: true }],
+            },
+            Function {
+                name: "family",
+                overloads: &[Overload { key: "ip_family", args: &[IP], result: Ty::Int, func: family, strict: true }],
+            },
+            Function {
+                name: "isUnspecified",
+                overloads: &[Overload { key: "ip_is_unspecified", args: &[IP], result: Ty::Bool, func: is_unspecified, strict: true }],
+            },
+            Function {
+                name: "isLoopback",
+                overloads: &[Overload { key: "ip_is_loopback", args: &[IP], result: Ty::Bool, func: is_loopback, strict: true }],
+            },
+            Function {
+                name: "isLinkLocalMulticast",
+                overloads: &[Overload { key: "ip_is_link_local_multicast", args: &[IP], result: Ty::Bool, func: is_link_local_multicast, strict: true }],
+            },
+            Function {
+                name: "isLinkLocalUnicast",
+                overloads: &[Overload { key: "ip_is_link_local_unicast", args: &[IP], result: Ty::Bool, func: is_link_local_unicast, strict: true }],
+            },
+            Function {
+                name: "isGlobalUnicast",
+                overloads: &[Overload { key: "ip_is_global_unicast", args: &[IP], result: Ty::Bool, func: is_global_unicast, strict: true }],
+            },
+            Function {
+                name: "cidr",
+                overloads: &[Overload { key: "string_to_cidr", args: &[Ty::String], result: CIDR, func: cidr, strict: true }],
+            },
+            Function {
+                name: "isCIDR",
+                overloads: &[Overload { key: "is_cidr", args: &[Ty::String], result: Ty::Bool, func: is_cidr, strict: true }],
+            },
+            Function {
+                name: "containsIP",
+                overloads: &[
+                    Overload { key: "cidr_contains_ip_ip", args: &[CIDR, IP], result: Ty::Bool, func: contains_ip, strict: true },
+                    Overload { key: "cidr_contains_ip_string", args: &[CIDR, Ty::String], result: Ty::Bool, func: contains_ip, strict: true },
+                ],
+            },
+            Function {
+                name: "containsCIDR",
+                overloads: &[
+                    Overload { key: "cidr_contains_cidr", args: &[CIDR, CIDR], result: Ty::Bool, func: contains_cidr, strict: true },
+                    Overload { key: "cidr_contains_cidr_string", args: &[CIDR, Ty::String], result: Ty::Bool, func: contains_cidr, strict: true },
+                ],
+            },
+            Function {
+                name: "prefixLength",
+                overloads: &[Overload { key: "cidr_prefix_length", args: &[CIDR], result: Ty::Int, func: prefix_length, strict: true }],
+            },
+            Function {
+                name: "masked",
+                overloads: &[Overload { key: "cidr_masked", args: &[CIDR], result: CIDR, func: masked, strict: true }],
+            },
+            Function {
+                name: "string",
+                overloads: &[
+                    Overload { key: "ip_to_string", args: &[IP], result: Ty::String, func: to_string, strict: true },
+                    Overload { key: "cidr_to_string", args: &[CIDR], result: Ty::String, func: to_string, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+/// An IP address, as made by `ip()`.
+struct Ip(IpAddr);
+
+impl Value for Ip {
+    fn ty(&self) -> Ty {
+        IP
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        &self.0
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.native_value().downcast_ref::<IpAddr>() == Some(&self.0))
+    }
+}
+
+/// An address and prefix length, as made by `cidr()`. The address keeps the bits
+/// past the prefix, which `masked()` clears.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn masked(&self) -> Cidr {
+        let addr = match self.addr {
+            IpAddr::V4(a) => IpAddr::V4(Ipv4Addr::from(u32::from(a) & mask(self.prefix, 32) as u32)),
+            IpAddr::V6(a) => IpAddr::V6(Ipv6Addr::from(u128::from(a) & mask(self.prefix, 128))),
+        };
+        Cidr { addr, prefix: self.prefix }
+    }
+
+    fn contains(&self, addr: &IpAddr) -> bool {
+        let other = Cidr { addr: *addr, prefix: self.prefix };
+        addr.is_ipv4() == self.addr.is_ipv4() && self.masked() == other.masked()
+    }
+}
+
+/// The netmask of a prefix length, in the low `bits` bits.
+fn mask(prefix: u8, bits: u32) -> u128 {
+    let ones = u128::MAX.checked_shl(bits - prefix as u32).unwrap_or(0);
+    ones & (u128::MAX >> (128 - bits))
+}
+
+impl Value for Cidr {
+    fn ty(&self) -> Ty {
+        CIDR
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.native_value().downcast_ref::<Cidr>() == Some(self))
+    }
+}
+
+fn parse_ip(s: &str) -> Result<IpAddr, String> {
+    let addr: IpAddr = s.parse().map_err(|_| format!("ParseAddr(\"{}\"): unable to parse IP", s))?;
+    if let IpAddr::V6(v6) = addr {
+        if v6.to_ipv4_mapped().is_some() {
+            return Err("IPv4-mapped IPv6 address is not allowed".to_string());
+        }
+    }
+    Ok(addr)
+}
+
+fn parse_cidr(s: &str) -> Result<Cidr, String> {
+    let invalid = || format!("netip.ParsePrefix(\"{}\"): invalid prefix", s);
+    let (addr, prefix) = s.split_once('/').ok_or_else(|| format!("netip.ParsePrefix(\"{}\"): no '/'", s))?;
+    let addr = parse_ip(addr)?;
+    if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) || (prefix.len() > 1 && prefix.starts_with('0')) {
+        return Err(invalid());
+    }
+    let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+    let bits = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix > bits {
+        return Err(invalid());
+    }
+    Ok(Cidr { addr, prefix })
+}
+
+fn string_arg(args: &[Val], i: usize) -> &str {
+    args[i].as_string().unwrap()
+}
+
+fn addr(val: &Val) -> &IpAddr {
+    val.native_value().downcast_ref::<IpAddr>().unwrap()
+}
+
+fn prefix(val: &Val) -> &Cidr {
+    val.native_value().downcast_ref::<Cidr>().unwrap()
+}
+
+fn ip(args: Vec<Val>) -> Val {
+    let s = string_arg(&args, 0);
+    match parse_ip(s) {
+        Ok(addr) => Val::new(Ip(addr)),
+        Err(e) => Error::invalid_argument(format!("IP Address \"{}\" parse error during conversion from string: {}", s, e)),
+    }
+}
+
+fn is_ip(args: Vec<Val>) -> Val {
+    Val::new_bool(parse_ip(string_arg(&args, 0)).is_ok())
+}
+
+/// Whether the string is the address as it would be printed, e.g. `2001:db8::1`
+/// rather than `2001:db8:0:0::1`.
+fn is_canonical(args: Vec<Val>) -> Val {
+    let s = string_arg(&args, 0);
+    match parse_ip(s) {
+        Ok(addr) => Val::new_bool(addr.to_string() == s),
+        Err(e) => Error::invalid_argument(format!("IP Address \"{}\" parse error during conversion from string: {}", s, e)),
+    }
+}
+
+fn family(args: Vec<Val>) -> Val {
+    Val::new_int(if addr(&args[0]).is_ipv4() { 4 } else { 6 })
+}
+
+fn is_unspecified(args: Vec<Val>) -> Val {
+    Val::new_bool(addr(&args[0]).is_unspecified())
+}
+
+fn is_loopback(args: Vec<Val>) -> Val {
+    Val::new_bool(addr(&args[0]).is_loopback())
+}
+
+fn is_link_local_multicast(args: Vec<Val>) -> Val {
+    Val::new_bool(match addr(&args[0]) {
+        IpAddr::V4(a) => a.octets()[..3] == [224, 0, 0],
+        IpAddr::V6(a) => a.segments()[0] & 0xff0f == 0xff02,
+    })
+}
+
+fn is_link_local_unicast(args: Vec<Val>) -> Val {
+    Val::new_bool(match addr(&args[0]) {
+        IpAddr::V4(a) => a.is_link_local(),
+        IpAddr::V6(a) => a.segments()[0] & 0xffc0 == 0xfe80,
+    })
+}
+
+/// Matches Go's `IsGlobalUnicast`, which includes private addresses.
+fn is_global_unicast(args: Vec<Val>) -> Val {
+    Val::new_bool(match addr(&args[0]) {
+        IpAddr::V4(a) => !(a.is_unspecified() || a.is_loopback() || a.is_multicast() || a.is_link_local() || a.is_broadcast()),
+        IpAddr::V6(a) => {
+            !(a.is_unspecified() || a.is_loopback() || a.is_multicast() || a.segments()[0] & 0xffc0 == 0xfe80)
+        }
+    })
+}
+
+fn cidr(args: Vec<Val>) -> Val {
+    let s = string_arg(&args, 0);
+    match parse_cidr(s) {
+        Ok(c) => Val::new(c),
+        Err(e) => Error::invalid_argument(format!("network address parse error during conversion from string: {}", e)),
+    }
+}
+
+fn is_cidr(args: Vec<Val>) -> Val {
+    Val::new_bool(parse_cidr(string_arg(&args, 0)).is_ok())
+}
+
+fn contains_ip(args: Vec<Val>) -> Val {
+    let addr = match args[1].as_string() {
+        Some(s) => match parse_ip(s) {
+            Ok(a) => a,
+            Err(e) => return Error::invalid_argument(format!("IP Address \"{}\" parse error during conversion from string: {}", s, e)),
+        },
+        None => *addr(&args[1]),
+    };
+    Val::new_bool(prefix(&args[0]).contains(&addr))
+}
+
+fn contains_cidr(args: Vec<Val>) -> Val {
+    let other = match args[1].as_string() {
+        Some(s) => match parse_cidr(s) {
+            Ok(c) => c,
+            Err(e) => return Error::invalid_argument(format!("network address parse error during conversion from string: {}", e)),
+        },
+        None => *prefix(&args[1]),
+    };
+    let cidr = prefix(&args[0]);
+    Val::new_bool(other.prefix >= cidr.prefix && cidr.contains(&other.addr))
+}
+
+fn cidr_ip(args: Vec<Val>) -> Val {
+    Val::new(Ip(prefix(&args[0]).addr))
+}
+
+fn prefix_length(args: Vec<Val>) -> Val {
+    Val::new_int(prefix(&args[0]).prefix as i64)
+}
+
+fn masked(args: Vec<Val>) -> Val {
+    Val::new(prefix(&args[0]).masked())
+}
+
+fn to_string(args: Vec<Val>) -> Val {
+    if let Some(c) = args[0].native_value().downcast_ref::<Cidr>() {
+        return Val::new_string(format!("{}/{}", c.addr, c.prefix));
+    }
+    Val::new_string(addr(&args[0]))
+}
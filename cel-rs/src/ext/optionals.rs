@@ -0,0 +1,67 @@
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::Error, ty::Ty},
+    Val,
+};
+
+/// Functions creating and unwrapping optional values: `optional.of(x)`,
+/// `optional.none()`, `opt.hasValue()`, `opt.value()` and `opt.orValue(default)`.
+pub struct Optionals;
+
+impl Library for Optionals {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "optional.of",
+                overloads: &[Overload { key: "optional_of", args: &[Ty::Dyn], result: Ty::Optional, func: of, strict: true }],
+            },
+            Function {
+                name: "optional.none",
+                overloads: &[Overload { key: "optional_none", args: &[], result: Ty::Optional, func: none, strict: true }],
+            },
+            Function {
+                name: "hasValue",
+                overloads: &[Overload { key: "optional_hasValue", args: &[Ty::Optional], result: Ty::Bool, func: has_value, strict: true }],
+            },
+            Function {
+                name: "value",
+                overloads: &[Overload { key: "optional_value", args: &[Ty::Optional], result: Ty::Dyn, func: value, strict: true }],
+            },
+            Function {
+                name: "orValue",
+                overloads: &[
+                    Overload { key: "optional_orValue_value", args: &[Ty::Optional, Ty::Dyn], result: Ty::Dyn, func: or_value, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+fn of(args: Vec<Val>) -> Val {
+    Val::new_optional(Some(args[0].clone()))
+}
+
+fn none(_: Vec<Val>) -> Val {
+    Val::new_optional(None)
+}
+
+fn has_value(args: Vec<Val>) -> Val {
+    Val::new_bool(args[0].as_optional().unwrap().is_some())
+}
+
+/// `opt.value()` unwraps the optional, and is an error when it is absent.
+fn value(args: Vec<Val>) -> Val {
+    match args[0].as_optional().unwrap() {
+        Some(v) => v.clone(),
+        None => Error::invalid_argument("optional.none() dereference".to_string()),
+    }
+}
+
+/// `opt.orValue(default)` unwraps the optional, or else returns the default.
+fn or_value(args: Vec<Val>) -> Val {
+    match args[0].as_optional().unwrap() {
+        Some(v) => v.clone(),
+        None => args[1].clone(),
+    }
+}
@@ -0,0 +1,238 @@
+use std::cmp::Ordering;
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::{Error, ErrorCode}, ty::Ty},
+    Val, Value,
+};
+
+const QUANTITY: Ty = Ty::Opaque("kubernetes.Quantity");
+
+/// Nanounits per unit, the finest precision a quantity keeps.
+const NANOS: i128 = 1_000_000_000;
+
+/// The Kubernetes quantity library: `quantity("500Mi")` values as used for
+/// resource requests and limits.
+///
+/// Quantities are kept as a 128-bit count of nanounits, so their magnitude is
+/// limited to about 1.7e29; `quantity()` fails with an overflow error beyond
+/// that, and `isQuantity()` returns false.
+pub struct Quantities;
+
+impl Library for Quantities {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "quantity",
+                overloads: &[Overload { key: "string_to_quantity", args: &[Ty::String], result: QUANTITY, func: quantity, strict: true }],
+            },
+            Function {
+                name: "isQuantity",
+                overloads: &[Overload { key: "is_quantity_string", args: &[Ty::String], result: Ty::Bool, func: is_quantity, strict: true }],
+            },
+            Function {
+                name: "isInteger",
+                overloads: &[Overload { key: "quantity_is_integer", args: &[QUANTITY], result: Ty::Bool, func: is_integer, strict: true }],
+            },
+            Function {
+                name: "asInteger",
+                overloads: &[Overload { key: "quantity_as_integer", args: &[QUANTITY], result: Ty::Int, func: as_integer, strict: true }],
+            },
+            Function {
+                name: "asApproximateFloat",
+                overloads: &[Overload { key: "quantity_as_approximate_float", args: &[QUANTITY], result: Ty::Double, func: as_approximate_float, strict: true }],
+            },
+            Function {
+                name: "compareTo",
+                overloads: &[Overload { key: "quantity_compare_to", args: &[QUANTITY, QUANTITY], result: Ty::Int, func: compare_to, strict: true }],
+            },
+            Function {
+                name: "add",
+                overloads: &[
+                    Overload { key: "quantity_add", args: &[QUANTITY, QUANTITY], result: QUANTITY, func: add, strict: true },
+                    Overload { key: "quantity_add_int", args: &[QUANTITY, Ty::Int], result: QUANTITY, func: add, strict: true },
+                ],
+            },
+            Function {
+                name: "sub",
+                overloads: &[
+                    Overload { key: "quantity_sub", args: &[QUANTITY, QUANTITY], result: QUANTITY, func: sub, strict: true },
+                    Overload { key: "quantity_sub_int", args: &[QUANTITY, Ty::Int], result: QUANTITY, func: sub, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+/// A quantity, counted in nanounits. Finer amounts are rounded away from zero, as
+/// Kubernetes does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Quantity(i128);
+
+impl Value for Quantity {
+    fn ty(&self) -> Ty {
+        QUANTITY
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.native_value().downcast_ref::<Quantity>() == Some(self))
+    }
+}
+
+/// Why a string is not a quantity.
+enum Invalid {
+    /// The string is not in the quantity syntax.
+    Syntax,
+    /// The string is a quantity too large to represent in nanounits.
+    Overflow,
+}
+
+/// Parses a quantity such as `1.5Gi`, `500m` or `12e6`.
+fn parse(s: &str) -> Result<Quantity, Invalid> {
+    let (negative, unsigned) = match s.as_bytes().first().ok_or(Invalid::Syntax)? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let end = unsigned.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(unsigned.len());
+    let (number, suffix) = unsigned.split_at(end);
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.contains('.') {
+        return Err(Invalid::Syntax);
+    }
+
+    let (binary, exponent) = parse_suffix(suffix).ok_or(Invalid::Syntax)?;
+    let nanos = scale(whole, fraction, binary, exponent).ok_or(Invalid::Overflow)?;
+    Ok(Quantity(if negative { -nanos } else { nanos }))
+}
+
+/// The nanounits in `whole.fraction` times 2^`binary` times 10^`exponent`, if
+/// they fit.
+fn scale(whole: &str, fraction: &str, binary: u32, exponent: i32) -> Option<i128> {
+    let mut digits: i128 = 0;
+    for d in whole.bytes().chain(fraction.bytes()) {
+        digits = digits.checked_mul(10)?.checked_add((d - b'0') as i128)?;
+    }
+    let digits = digits.checked_mul(1i128.checked_shl(binary)?)?;
+    let scale = exponent.checked_add(9)?.checked_sub(i32::try_from(fraction.len()).ok()?)?;
+    Some(if scale >= 0 {
+        digits.checked_mul(10i128.checked_pow(scale as u32)?)?
+    } else {
+        match 10i128.checked_pow(scale.unsigned_abs()) {
+            Some(divisor) => digits / divisor + (digits % divisor != 0) as i128,
+            None => (digits != 0) as i128,
+        }
+    })
+}
+
+/// The multiplier of a suffix, as a power of two and a power of ten.
+fn parse_suffix(suffix: &str) -> Option<(u32, i32)> {
+    Some(match suffix {
+        "" => (0, 0),
+        "Ki" => (10, 0),
+        "Mi" => (20, 0),
+        "Gi" => (30, 0),
+        "Ti" => (40, 0),
+        "Pi" => (50, 0),
+        "Ei" => (60, 0),
+        "n" => (0, -9),
+        "u" => (0, -6),
+        "m" => (0, -3),
+        "k" => (0, 3),
+        "M" => (0, 6),
+        "G" => (0, 9),
+        "T" => (0, 12),
+        "P" => (0, 15),
+        "E" => (0, 18),
+        _ => {
+            let exponent = suffix.strip_prefix(['e', 'E'])?;
+            let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            // The digits are valid, so parsing only fails when the exponent is
+            // out of range, which saturates.
+            let saturated = if exponent.starts_with('-') { i32::MIN } else { i32::MAX };
+            (0, exponent.parse().unwrap_or(saturated))
+        }
+    })
+}
+
+fn amount(val: &Val) -> Quantity {
+    *val.native_value().downcast_ref::<Quantity>().unwrap()
+}
+
+/// The right operand of `add` and `sub`, either a quantity or an int.
+fn operand(val: &Val) -> Quantity {
+    match val.as_int() {
+        Some(i) => Quantity(*i as i128 * NANOS),
+        None => amount(val),
+    }
+}
+
+fn quantity(args: Vec<Val>) -> Val {
+    match parse(args[0].as_string().unwrap()) {
+        Ok(q) => Val::new(q),
+        Err(Invalid::Syntax) => Error::invalid_argument(
+            "quantities must match the regular expression '^([+-]?[0-9.]+)([eEinumkKMGTP]*[-+]?[0-9]*)$'".to_string(),
+        ),
+        Err(Invalid::Overflow) => Error::with_code(
+            ErrorCode::Overflow,
+            format!("quantities must be between -{max} and {max}", max = i128::MAX / NANOS),
+        ),
+    }
+}
+
+fn is_quantity(args: Vec<Val>) -> Val {
+    Val::new_bool(parse(args[0].as_string().unwrap()).is_ok())
+}
+
+/// The whole number of units, if the quantity is one that fits an int.
+fn integer(q: Quantity) -> Option<i64> {
+    if q.0 % NANOS != 0 {
+        return None;
+    }
+    i64::try_from(q.0 / NANOS).ok()
+}
+
+fn is_integer(args: Vec<Val>) -> Val {
+    Val::new_bool(integer(amount(&args[0])).is_some())
+}
+
+fn as_integer(args: Vec<Val>) -> Val {
+    match integer(amount(&args[0])) {
+        Some(i) => Val::new_int(i),
+        None => Error::invalid_argument("cannot convert value to integer".to_string()),
+    }
+}
+
+fn as_approximate_float(args: Vec<Val>) -> Val {
+    Val::new_double(amount(&args[0]).0 as f64 / NANOS as f64)
+}
+
+fn compare_to(args: Vec<Val>) -> Val {
+    Val::new_int(match amount(&args[0]).cmp(&amount(&args[1])) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    })
+}
+
+fn add(args: Vec<Val>) -> Val {
+    match amount(&args[0]).0.checked_add(operand(&args[1]).0) {
+        Some(n) => Val::new(Quantity(n)),
+        None => Error::overflow("quantity"),
+    }
+}
+
+fn sub(args: Vec<Val>) -> Val {
+    match amount(&args[0]).0.checked_sub(operand(&args[1]).0) {
+        Some(n) => Val::new(Quantity(n)),
+        None => Error::overflow("quantity"),
+    }
+}
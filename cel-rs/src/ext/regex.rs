@@ -0,0 +1,156 @@
+use std::rc::Rc;
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    pattern::compile,
+    value::{error::Error, ty::Ty},
+    Val,
+};
+
+/// Regular expression functions matching the cel-go regex extension, called with
+/// the `regex.` namespace. Patterns use RE2 syntax.
+pub struct Regex;
+
+impl Library for Regex {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "regex.extract",
+                overloads: &[
+                    Overload { key: "regex_extract_string_string", args: &[Ty::String, Ty::String], result: Ty::Optional, func: extract, strict: true },
+                ],
+            },
+            Function {
+                name: "regex.extractAll",
+                overloads: &[
+                    Overload { key: "regex_extractAll_string_string", args: &[Ty::String, Ty::String], result: Ty::List, func: extract_all, strict: true },
+                ],
+            },
+            Function {
+                name: "regex.replace",
+                overloads: &[
+                    Overload { key: "regex_replace_string_string_string", args: &[Ty::String, Ty::String, Ty::String], result: Ty::String, func: replace, strict: true },
+                    Overload { key: "regex_replace_string_string_string_int", args: &[Ty::String, Ty::String, Ty::String, Ty::Int], result: Ty::String, func: replace, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+/// Compiles a pattern for extraction, which allows at most one capture group.
+fn extraction_pattern(pattern: &str) -> Result<::regex::Regex, Val> {
+    let re = compile(pattern)?;
+    if re.captures_len() > 2 {
+        return Err(Error::invalid_argument(format!(
+            "regular expression has more than one capturing group: '{}'",
+            pattern
+        )));
+    }
+    Ok(re)
+}
+
+/// The text extracted from a match: the capture group if the pattern has one,
+/// otherwise the whole match.
+fn extracted<'t>(captures: &::regex::Captures<'t>) -> Option<&'t str> {
+    let group = if captures.len() > 1 { 1 } else { 0 };
+    captures.get(group).map(|m| m.as_str())
+}
+
+/// `regex.extract(s, re)` is the first match, or `optional.none()` without one.
+fn extract(args: Vec<Val>) -> Val {
+    let (target, pattern) = (args[0].as_string().unwrap(), args[1].as_string().unwrap());
+    let re = match extraction_pattern(pattern) {
+        Ok(re) => re,
+        Err(e) => return e,
+    };
+    let found = re.captures(target).and_then(|c| extracted(&c).map(Val::new_string));
+    Val::new_optional(found)
+}
+
+/// `regex.extractAll(s, re)` lists every match, skipping those where the capture
+/// group took no part.
+fn extract_all(args: Vec<Val>) -> Val {
+    let (target, pattern) = (args[0].as_string().unwrap(), args[1].as_string().unwrap());
+    let re = match extraction_pattern(pattern) {
+        Ok(re) => re,
+        Err(e) => return e,
+    };
+    let found = re.captures_iter(target).filter_map(|c| extracted(&c).map(Val::new_string));
+    Val::new_list(Rc::new(found.collect()))
+}
+
+/// A piece of a replacement string.
+enum Replacement {
+    Literal(String),
+    Group(usize),
+}
+
+/// Parses a replacement such as `\1-\2`, where `\N` stands for capture group N
+/// and `\\` for a backslash.
+fn parse_replacement(replacement: &str, groups: usize) -> Result<Vec<Replacement>, Val> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            literal.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => literal.push('\\'),
+            Some(d) if d.is_ascii_digit() => {
+                let group = d.to_digit(10).unwrap() as usize;
+                if group >= groups {
+                    return Err(Error::invalid_argument(format!(
+                        "replacement string references group {} but regex has {} group(s)",
+                        group,
+                        groups - 1
+                    )));
+                }
+                parts.push(Replacement::Literal(std::mem::take(&mut literal)));
+                parts.push(Replacement::Group(group));
+            }
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "invalid replacement string: '{}' \\ must be followed by a digit or \\",
+                    replacement
+                )))
+            }
+        }
+    }
+    parts.push(Replacement::Literal(literal));
+    Ok(parts)
+}
+
+/// `regex.replace(s, re, replacement)` replaces every match, or with a count, the
+/// first `count` matches; a negative count means all of them.
+fn replace(args: Vec<Val>) -> Val {
+    let (target, pattern) = (args[0].as_string().unwrap(), args[1].as_string().unwrap());
+    let count = args.get(3).map_or(-1, |c| *c.as_int().unwrap());
+    let re = match compile(pattern) {
+        Ok(re) => re,
+        Err(e) => return e,
+    };
+    let replacement = match parse_replacement(args[2].as_string().unwrap(), re.captures_len()) {
+        Ok(r) => r,
+        Err(e) => return e,
+    };
+    let limit = usize::try_from(count).unwrap_or(usize::MAX);
+
+    let mut out = String::with_capacity(target.len());
+    let mut last = 0;
+    for captures in re.captures_iter(target).take(limit) {
+        let whole = captures.get(0).unwrap();
+        out.push_str(&target[last..whole.start()]);
+        for part in replacement.iter() {
+            match part {
+                Replacement::Literal(s) => out.push_str(s),
+                Replacement::Group(g) => out.push_str(captures.get(*g).map_or("", |m| m.as_str())),
+            }
+        }
+        last = whole.end();
+    }
+    out.push_str(&target[last..]);
+    Val::new_string(out)
+}
@@ -0,0 +1,246 @@
+use std::cmp::Ordering;
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::Error, ty::Ty},
+    Val, Value,
+};
+
+const SEMVER: Ty = Ty::Opaque("kubernetes.Semver");
+
+/// The Kubernetes semver library: `semver("1.2.3")` values, ordered by semantic
+/// versioning precedence. Passing `true` as the second argument of `semver` or
+/// `isSemver` accepts versions such as `v1.02`, read as `1.2.0`.
+pub struct Semver;
+
+impl Library for Semver {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "semver",
+                overloads: &[
+                    Overload { key: "string_to_semver", args: &[Ty::String], result: SEMVER, func: semver, strict: true },
+                    Overload { key: "string_bool_to_semver", args: &[Ty::String, Ty::Bool], result: SEMVER, func: semver, strict: true },
+                ],
+            },
+            Function {
+                name: "isSemver",
+                overloads: &[
+                    Overload { key: "is_semver_string", args: &[Ty::String], result: Ty::Bool, func: is_semver, strict: true },
+                    Overload { key: "is_semver_string_bool", args: &[Ty::String, Ty::Bool], result: Ty::Bool, func: is_semver, strict: true },
+                ],
+            },
+            Function {
+                name: "major",
+                overloads: &[Overload { key: "semver_major", args: &[SEMVER], result: Ty::Int, func: major, strict: true }],
+            },
+            Function {
+                name: "minor",
+                overloads: &[Overload { key: "semver_minor", args: &[SEMVER], result: Ty::Int, func: minor, strict: true }],
+            },
+            Function {
+                name: "patch",
+                overloads: &[Overload { key: "semver_patch", args: &[SEMVER], result: Ty::Int, func: patch, strict: true }],
+            },
+            Function {
+                name: "isLessThan",
+                overloads: &[Overload { key: "semver_is_less_than", args: &[SEMVER, SEMVER], result: Ty::Bool, func: is_less_than, strict: true }],
+            },
+            Function {
+                name: "isGreaterThan",
+                overloads: &[Overload { key: "semver_is_greater_than", args: &[SEMVER, SEMVER], result: Ty::Bool, func: is_greater_than, strict: true }],
+            },
+            Function {
+                name: "compareTo",
+                overloads: &[Overload { key: "semver_compare_to", args: &[SEMVER, SEMVER], result: Ty::Int, func: compare_to, strict: true }],
+            },
+        ]
+    }
+}
+
+/// A pre-release identifier. Numeric identifiers sort before alphanumeric ones.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+/// A semantic version. Build metadata is checked when parsing, then dropped since
+/// it takes no part in comparisons.
+#[derive(Debug, Clone)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<Identifier>,
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A release has precedence over its pre-releases.
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                _ => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl Value for Version {
+    fn ty(&self) -> Ty {
+        SEMVER
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.native_value().downcast_ref::<Version>() == Some(self))
+    }
+}
+
+/// Parses a version number, which may not have leading zeros.
+fn parse_number(s: &str, part: &str) -> Result<u64, String> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("invalid character(s) found in {} number '{}'", part, s));
+    }
+    if s.len() > 1 && s.starts_with('0') {
+        return Err(format!("{} number must not contain leading zeroes '{}'", part, s));
+    }
+    s.parse().map_err(|_| format!("{} number '{}' is too large", part, s))
+}
+
+fn parse_identifiers(s: &str, what: &str) -> Result<Vec<String>, String> {
+    s.split('.')
+        .map(|id| {
+            if id.is_empty() || !id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                return Err(format!("invalid character(s) found in {} '{}'", what, id));
+            }
+            Ok(id.to_string())
+        })
+        .collect()
+}
+
+/// Splits `1.2.3-rc.1+build` into `1.2.3` and the `-rc.1+build` that follows.
+fn split_core(s: &str) -> (&str, &str) {
+    s.split_at(s.find(['-', '+']).unwrap_or(s.len()))
+}
+
+fn parse(s: &str) -> Result<Version, String> {
+    let (core, rest) = split_core(s);
+    let parts: Vec<&str> = core.split('.').collect();
+    if parts.len() != 3 {
+        return Err("no Major.Minor.Patch elements found".to_string());
+    }
+    let rest = match rest.split_once('+') {
+        Some((rest, build)) => parse_identifiers(build, "build metadata").map(|_| rest)?,
+        None => rest,
+    };
+    let pre = match rest.strip_prefix('-') {
+        Some(pre) => parse_identifiers(pre, "pre-release")?
+            .into_iter()
+            .map(|id| match id.bytes().all(|b| b.is_ascii_digit()) {
+                true => parse_number(&id, "pre-release").map(Identifier::Numeric),
+                false => Ok(Identifier::Alphanumeric(id)),
+            })
+            .collect::<Result<_, _>>()?,
+        None => vec![],
+    };
+    Ok(Version {
+        major: parse_number(parts[0], "major")?,
+        minor: parse_number(parts[1], "minor")?,
+        patch: parse_number(parts[2], "patch")?,
+        pre,
+    })
+}
+
+/// Drops a leading `v`, fills in a missing minor or patch number and strips
+/// leading zeros, so `v1.02` becomes `1.2.0`.
+fn normalize(s: &str) -> String {
+    let s = s.strip_prefix('v').unwrap_or(s);
+    let (core, rest) = split_core(s);
+    let mut parts: Vec<String> = core
+        .split('.')
+        .map(|p| match p.trim_start_matches('0') {
+            "" if !p.is_empty() => "0".to_string(),
+            trimmed => trimmed.to_string(),
+        })
+        .collect();
+    if parts.len() < 3 {
+        parts.resize(3, "0".to_string());
+    }
+    format!("{}{}", parts.join("."), rest)
+}
+
+fn parse_arg(args: &[Val]) -> Result<Version, String> {
+    let s = args[0].as_string().unwrap();
+    match args.get(1).and_then(|n| n.as_bool()) {
+        Some(true) => parse(&normalize(s)),
+        _ => parse(s),
+    }
+}
+
+fn version(val: &Val) -> &Version {
+    val.native_value().downcast_ref::<Version>().unwrap()
+}
+
+fn semver(args: Vec<Val>) -> Val {
+    match parse_arg(&args) {
+        Ok(v) => Val::new(v),
+        Err(e) => Error::invalid_argument(format!("invalid semver '{}': {}", args[0].as_string().unwrap(), e)),
+    }
+}
+
+fn is_semver(args: Vec<Val>) -> Val {
+    Val::new_bool(parse_arg(&args).is_ok())
+}
+
+fn int(n: u64) -> Val {
+    i64::try_from(n).map_or_else(|_| Error::overflow("integer"), Val::new_int)
+}
+
+fn major(args: Vec<Val>) -> Val {
+    int(version(&args[0]).major)
+}
+
+fn minor(args: Vec<Val>) -> Val {
+    int(version(&args[0]).minor)
+}
+
+fn patch(args: Vec<Val>) -> Val {
+    int(version(&args[0]).patch)
+}
+
+fn is_less_than(args: Vec<Val>) -> Val {
+    Val::new_bool(version(&args[0]) < version(&args[1]))
+}
+
+fn is_greater_than(args: Vec<Val>) -> Val {
+    Val::new_bool(version(&args[0]) > version(&args[1]))
+}
+
+fn compare_to(args: Vec<Val>) -> Val {
+    Val::new_int(match version(&args[0]).cmp(version(&args[1])) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    })
+}
@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::ty::Ty,
+    Val,
+};
+
+/// Set relations between lists, matching the cel-go sets extension. Elements are
+/// compared with CEL equality, so `sets.contains([1], [1.0])` holds.
+pub struct Sets;
+
+impl Library for Sets {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "sets.contains",
+                overloads: &[
+                    Overload { key: "list_sets_contains_list", args: &[Ty::List, Ty::List], result: Ty::Bool, func: contains, strict: true },
+                ],
+            },
+            Function {
+                name: "sets.equivalent",
+                overloads: &[
+                    Overload { key: "list_sets_equivalent_list", args: &[Ty::List, Ty::List], result: Ty::Bool, func: equivalent, strict: true },
+                ],
+            },
+            Function {
+                name: "sets.intersects",
+                overloads: &[
+                    Overload { key: "list_sets_intersects_list", args: &[Ty::List, Ty::List], result: Ty::Bool, func: intersects, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+fn set(list: &Val) -> HashSet<&Val> {
+    list.as_list().unwrap().iter().collect()
+}
+
+fn is_subset(set: &HashSet<&Val>, list: &Val) -> bool {
+    list.as_list().unwrap().iter().all(|e| set.contains(e))
+}
+
+/// Whether every element of the second list is in the first.
+fn contains(args: Vec<Val>) -> Val {
+    Val::new_bool(is_subset(&set(&args[0]), &args[1]))
+}
+
+/// Whether the lists hold the same elements, ignoring order and duplicates.
+fn equivalent(args: Vec<Val>) -> Val {
+    Val::new_bool(is_subset(&set(&args[0]), &args[1]) && is_subset(&set(&args[1]), &args[0]))
+}
+
+/// Whether the lists have an element in common.
+fn intersects(args: Vec<Val>) -> Val {
+    let set = set(&args[0]);
+    Val::new_bool(args[1].as_list().unwrap().iter().any(|e| set.contains(e)))
+}
@@ -0,0 +1,259 @@
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::Error, ty::Ty},
+    Val,
+};
+
+/// String manipulation functions, matching the cel-go strings extension. Indexes
+/// count code points rather than bytes.
+pub struct Strings;
+
+impl Library for Strings {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "charAt",
+                overloads: &[
+                    Overload { key: "string_char_at_int", args: &[Ty::String, Ty::Int], result: Ty::String, func: char_at, strict: true },
+                ],
+            },
+            Function {
+                name: "indexOf",
+                overloads: &[
+                    Overload { key: "string_index_of_string", args: &[Ty::String, Ty::String], result: Ty::Int, func: index_of, strict: true },
+                    Overload { key: "string_index_of_string_int", args: &[Ty::String, Ty::String, Ty::Int], result: Ty::Int, func: index_of, strict: true },
+                ],
+            },
+            Function {
+                name: "lastIndexOf",
+                overloads: &[
+                    Overload { key: "string_last_index_of_string", args: &[Ty::String, Ty::String], result: Ty::Int, func: last_index_of, strict: true },
+                    Overload { key: "string_last_index_of_string_int", args: &[Ty::String, Ty::String, Ty::Int], result: Ty::Int, func: last_index_of, strict: true },
+                ],
+            },
+            Function {
+                name: "lowerAscii",
+                overloads: &[
+                    Overload { key: "string_lower_ascii", args: &[Ty::String], result: Ty::String, func: lower_ascii, strict: true },
+                ],
+            },
+            Function {
+                name: "upperAscii",
+                overloads: &[
+                    Overload { key: "string_upper_ascii", args: &[Ty::String], result: Ty::String, func: upper_ascii, strict: true },
+                ],
+            },
+            Function {
+                name: "replace",
+                overloads: &[
+                    Overload { key: "string_replace_string_string", args: &[Ty::String, Ty::String, Ty::String], result: Ty::String, func: replace, strict: true },
+                    Overload { key: "string_replace_string_string_int", args: &[Ty::String, Ty::String, Ty::String, Ty::Int], result: Ty::String, func: replace, strict: true },
+                ],
+            },
+            Function {
+                name: "split",
+                overloads: &[
+                    Overload { key: "string_split_string", args: &[Ty::String, Ty::String], result: Ty::List, func: split, strict: true },
+                    Overload { key: "string_split_string_int", args: &[Ty::String, Ty::String, Ty::Int], result: Ty::List, func: split, strict: true },
+                ],
+            },
+            Function {
+                name: "substring",
+                overloads: &[
+                    Overload { key: "string_substring_int", args: &[Ty::String, Ty::Int], result: Ty::String, func: substring, strict: true },
+                    Overload { key: "string_substring_int_int", args: &[Ty::String, Ty::Int, Ty::Int], result: Ty::String, func: substring, strict: true },
+                ],
+            },
+            Function {
+                name: "trim",
+                overloads: &[
+                    Overload { key: "string_trim", args: &[Ty::String], result: Ty::String, func: trim, strict: true },
+                ],
+            },
+            Function {
+                name: "join",
+                overloads: &[
+                    Overload { key: "list_join", args: &[Ty::List], result: Ty::String, func: join, strict: true },
+                    Overload { key: "list_join_string", args: &[Ty::List, Ty::String], result: Ty::String, func: join, strict: true },
+                ],
+            },
+            Function {
+                name: "reverse",
+                overloads: &[
+                    Overload { key: "string_reverse", args: &[Ty::String], result: Ty::String, func: reverse, strict: true },
+                ],
+            },
+            Function {
+                name: "format",
+                overloads: &[
+                    Overload { key: "string_format", args: &[Ty::String, Ty::List], result: Ty::String, func: super::format::format, strict: true },
+                ],
+            },
+            Function {
+                name: "strings.quote",
+                overloads: &[
+                    Overload { key: "strings_quote", args: &[Ty::String], result: Ty::String, func: quote, strict: true },
+                ],
+            },
+        ]
+    }
+}
+
+fn string(arg: &Val) -> &str {
+    arg.as_string().unwrap()
+}
+
+fn int(arg: &Val) -> i64 {
+    *arg.as_int().unwrap()
+}
+
+fn out_of_range(index: i64) -> Val {
+    Error::invalid_argument(format!("index out of range: {}", index))
+}
+
+fn char_at(args: Vec<Val>) -> Val {
+    let chars: Vec<char> = string(&args[0]).chars().collect();
+    let index = int(&args[1]);
+    match usize::try_from(index) {
+        Ok(i) if i < chars.len() => Val::new_string(chars[i]),
+        Ok(i) if i == chars.len() => Val::new_string(""),
+        _ => out_of_range(index),
+    }
+}
+
+/// Code-point index of the first occurrence of `sub` in `chars` at or after `from`.
+fn find(chars: &[char], sub: &[char], from: usize) -> Option<usize> {
+    (from..=chars.len().checked_sub(sub.len())?).find(|&i| chars[i..].starts_with(sub))
+}
+
+fn index_of(args: Vec<Val>) -> Val {
+    let chars: Vec<char> = string(&args[0]).chars().collect();
+    let sub: Vec<char> = string(&args[1]).chars().collect();
+    let offset = args.get(2).map_or(0, int);
+    if sub.is_empty() {
+        return Val::new_int(offset);
+    }
+    match usize::try_from(offset) {
+        Ok(from) if from < chars.len() => Val::new_int(find(&chars, &sub, from).map_or(-1, |i| i as i64)),
+        _ => out_of_range(offset),
+    }
+}
+
+fn last_index_of(args: Vec<Val>) -> Val {
+    let chars: Vec<char> = string(&args[0]).chars().collect();
+    let sub: Vec<char> = string(&args[1]).chars().collect();
+    let offset = args.get(2).map_or(chars.len() as i64, int);
+    if sub.is_empty() {
+        return Val::new_int(offset);
+    }
+    let from = match usize::try_from(offset) {
+        Ok(from) if from < chars.len() || args.len() == 2 => from,
+        _ => return out_of_range(offset),
+    };
+    let last = match chars.len().checked_sub(sub.len()) {
+        Some(last) => last.min(from),
+        None => return Val::new_int(-1),
+    };
+    let found = (0..=last).rev().find(|&i| chars[i..].starts_with(&sub));
+    Val::new_int(found.map_or(-1, |i| i as i64))
+}
+
+fn lower_ascii(args: Vec<Val>) -> Val {
+    Val::new_string(string(&args[0]).to_ascii_lowercase())
+}
+
+fn upper_ascii(args: Vec<Val>) -> Val {
+    Val::new_string(string(&args[0]).to_ascii_uppercase())
+}
+
+/// Replaces the first `n` occurrences, or all of them when `n` is negative.
+fn replace(args: Vec<Val>) -> Val {
+    let (s, from, to) = (string(&args[0]), string(&args[1]), string(&args[2]));
+    match args.get(3).map(int) {
+        Some(n) if n >= 0 => Val::new_string(s.replacen(from, to, n as usize)),
+        _ => Val::new_string(s.replace(from, to)),
+    }
+}
+
+/// Splits into at most `n` parts, or all of them when `n` is negative. An empty
+/// separator splits between code points.
+fn split(args: Vec<Val>) -> Val {
+    let (s, sep) = (string(&args[0]), string(&args[1]));
+    let limit = match args.get(2).map(int) {
+        Some(0) => return Val::new_list(Vec::new().into()),
+        Some(n) if n > 0 => n as usize,
+        _ => usize::MAX,
+    };
+    let parts: Vec<Val> = if sep.is_empty() {
+        let mut parts: Vec<Val> = s.chars().take(limit - 1).map(Val::new_string).collect();
+        let rest: String = s.chars().skip(limit - 1).collect();
+        if !rest.is_empty() {
+            parts.push(Val::new_string(rest));
+        }
+        parts
+    } else {
+        s.splitn(limit, sep).map(Val::new_string).collect()
+    };
+    Val::new_list(parts.into())
+}
+
+fn substring(args: Vec<Val>) -> Val {
+    let chars: Vec<char> = string(&args[0]).chars().collect();
+    let start = int(&args[1]);
+    let end = args.get(2).map_or(chars.len() as i64, int);
+    let in_range = |i: i64| usize::try_from(i).ok().filter(|i| *i <= chars.len());
+    let Some(from) = in_range(start) else {
+        return out_of_range(start);
+    };
+    let Some(to) = in_range(end) else {
+        return out_of_range(end);
+    };
+    if from > to {
+        return Error::invalid_argument(format!("invalid substring range. start: {}, end: {}", start, end));
+    }
+    Val::new_string(chars[from..to].iter().collect::<String>())
+}
+
+fn trim(args: Vec<Val>) -> Val {
+    Val::new_string(string(&args[0]).trim())
+}
+
+fn join(args: Vec<Val>) -> Val {
+    let sep = args.get(1).map_or("", string);
+    let parts: Option<Vec<&str>> = args[0].as_list().unwrap().iter().map(|v| v.as_string()).collect();
+    match parts {
+        Some(parts) => Val::new_string(parts.join(sep)),
+        None => Error::no_matching_overload("join"),
+    }
+}
+
+fn reverse(args: Vec<Val>) -> Val {
+    Val::new_string(string(&args[0]).chars().rev().collect::<String>())
+}
+
+fn quote(args: Vec<Val>) -> Val {
+    Val::new_string(quoted(string(&args[0])))
+}
+
+/// The string as a double-quoted CEL string literal. Quotes, backslashes and the
+/// control characters with a single-letter escape such as `\n` are escaped.
+pub(super) fn quoted(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '\x07' => quoted.push_str("\\a"),
+            '\x08' => quoted.push_str("\\b"),
+            '\x0c' => quoted.push_str("\\f"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\x0b' => quoted.push_str("\\v"),
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{
+    context::Library,
+    function::{Function, Overload},
+    value::{error::Error, ty::Ty},
+    Val, Value,
+};
+
+const URL: Ty = Ty::Opaque("kubernetes.URL");
+
+/// The Kubernetes URL library: `url(s)` parses `s` the way Go's
+/// `url.ParseRequestURI` does, as an absolute URI or an absolute path.
+pub struct Urls;
+
+impl Library for Urls {
+    fn functions(&self) -> Vec<Function> {
+        vec![
+            Function {
+                name: "url",
+                overloads: &[Overload { key: "string_to_url", args: &[Ty::String], result: URL, func: url, strict: true }],
+            },
+            Function {
+                name: "isURL",
+                overloads: &[Overload { key: "is_url_string", args: &[Ty::String], result: Ty::Bool, func: is_url, strict: true }],
+            },
+            Function {
+                name: "getScheme",
+                overloads: &[Overload { key: "url_get_scheme", args: &[URL], result: Ty::String, func: get_scheme, strict: true }],
+            },
+            Function {
+                name: "getHost",
+                overloads: &[Overload { key: "url_get_host", args: &[URL], result: Ty::String, func: get_host, strict: true }],
+            },
+            Function {
+                name: "getHostname",
+                overloads: &[Overload { key: "url_get_hostname", args: &[URL], result: Ty::String, func: get_hostname, strict: true }],
+            },
+            Function {
+                name: "getPort",
+                overloads: &[Overload { key: "url_get_port", args: &[URL], result: Ty::String, func: get_port, strict: true }],
+            },
+            Function {
+                name: "getEscapedPath",
+                overloads: &[Overload { key: "url_get_escaped_path", args: &[URL], result: Ty::String, func: get_escaped_path, strict: true }],
+            },
+            Function {
+                name: "getQuery",
+                overloads: &[Overload { key: "url_get_query", args: &[URL], result: Ty::Map, func: get_query, strict: true }],
+            },
+        ]
+    }
+}
+
+/// The parts of a parsed URL, as written in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Url {
+    scheme: String,
+    /// Host and port, e.g. `example.com:80` or `[::1]:80`.
+    host: String,
+    path: String,
+    query: String,
+}
+
+impl Value for Url {
+    fn ty(&self) -> Ty {
+        URL
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.native_value().downcast_ref::<Url>() == Some(self))
+    }
+}
+
+impl Url {
+    fn parse(s: &str) -> Result<Url, String> {
+        if s.bytes().any(|b| b < 0x20 || b == 0x7f) {
+            return Err("net/url: invalid control character in URL".to_string());
+        }
+        if s.is_empty() {
+            return Err("empty url".to_string());
+        }
+        let (scheme, rest) = split_scheme(s)?;
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, query),
+            None => (rest, ""),
+        };
+        if !rest.starts_with('/') {
+            return Err("invalid URI for request".to_string());
+        }
+        let (host, path) = match rest.strip_prefix("//") {
+            Some(authority) if !scheme.is_empty() => {
+                let end = authority.find('/').unwrap_or(authority.len());
+                let (authority, path) = authority.split_at(end);
+                let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+                check_host(host)?;
+                (host, path)
+            }
+            _ => ("", rest),
+        };
+        unescape(path, false).ok_or_else(|| format!("invalid URL escape in '{}'", path))?;
+        Ok(Url {
+            scheme: scheme.to_ascii_lowercase(),
+            host: host.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+        })
+    }
+
+    /// The host without the port, or the brackets of an IPv6 address.
+    fn hostname(&self) -> &str {
+        if let Some(bracketed) = self.host.strip_prefix('[') {
+            return bracketed.split_once(']').map_or(bracketed, |(h, _)| h);
+        }
+        match self.host.split_once(':') {
+            Some((host, _)) => host,
+            None => &self.host,
+        }
+    }
+
+    fn port(&self) -> &str {
+        let after_host = match self.host.strip_prefix('[') {
+            Some(bracketed) => bracketed.split_once(']').map_or("", |(_, rest)| rest),
+            None => self.host.find(':').map_or("", |i| &self.host[i..]),
+        };
+        after_host.strip_prefix(':').unwrap_or_default()
+    }
+}
+
+/// Splits off the scheme, which is empty for a path such as `/a/b`.
+fn split_scheme(s: &str) -> Result<(&str, &str), String> {
+    for (i, c) in s.char_indices() {
+        match c {
+            'a'..='z' | 'A'..='Z' => {}
+            '0'..='9' | '+' | '-' | '.' if i > 0 => {}
+            ':' if i == 0 => return Err("missing protocol scheme".to_string()),
+            ':' => return Ok((&s[..i], &s[i + 1..])),
+            _ => return Ok(("", s)),
+        }
+    }
+    Ok(("", s))
+}
+
+fn check_host(host: &str) -> Result<(), String> {
+    let port = match host.strip_prefix('[') {
+        Some(bracketed) => match bracketed.split_once(']') {
+            Some((_, rest)) => rest,
+            None => return Err("missing ']' in host".to_string()),
+        },
+        None => host.find(':').map_or("", |i| &host[i..]),
+    };
+    if !port.is_empty() && !port.strip_prefix(':').is_some_and(|p| p.bytes().all(|b| b.is_ascii_digit())) {
+        return Err(format!("invalid port \"{}\" after host", port));
+    }
+    Ok(())
+}
+
+/// Decodes `%XX` escapes, and with `plus_as_space`, `+` as in query strings.
+fn unescape(s: &str, plus_as_space: bool) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = s.get(i + 1..i + 3)?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+                continue;
+            }
+            b'+' if plus_as_space => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
+
+fn url(args: Vec<Val>) -> Val {
+    match Url::parse(args[0].as_string().unwrap()) {
+        Ok(u) => Val::new(u),
+        Err(e) => Error::invalid_argument(format!("URL parse error during conversion from string: {}", e)),
+    }
+}
+
+fn is_url(args: Vec<Val>) -> Val {
+    Val::new_bool(Url::parse(args[0].as_string().unwrap()).is_ok())
+}
+
+fn parsed(val: &Val) -> &Url {
+    val.native_value().downcast_ref::<Url>().unwrap()
+}
+
+fn get_scheme(args: Vec<Val>) -> Val {
+    Val::new_string(&parsed(&args[0]).scheme)
+}
+
+fn get_host(args: Vec<Val>) -> Val {
+    Val::new_string(&parsed(&args[0]).host)
+}
+
+fn get_hostname(args: Vec<Val>) -> Val {
+    Val::new_string(parsed(&args[0]).hostname())
+}
+
+fn get_port(args: Vec<Val>) -> Val {
+    Val::new_string(parsed(&args[0]).port())
+}
+
+fn get_escaped_path(args: Vec<Val>) -> Val {
+    Val::new_string(&parsed(&args[0]).path)
+}
+
+/// The query parameters, each with every value it was given. Malformed pairs are
+/// skipped.
+fn get_query(args: Vec<Val>) -> Val {
+    let mut params: HashMap<String, Vec<Val>> = HashMap::new();
+    for pair in parsed(&args[0]).query.split('&').filter(|p| !p.is_empty() && !p.contains(';')) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if let (Some(key), Some(value)) = (unescape(key, true), unescape(value, true)) {
+            params.entry(key).or_default().push(Val::new_string(value));
+        }
+    }
+    let map = params
+        .into_iter()
+        .map(|(k, v)| (Val::new_string(k), Val::new_list(Rc::new(v))))
+        .collect();
+    Val::new_map(Rc::new(map))
+}
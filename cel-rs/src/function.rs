@@ -1,4 +1,4 @@
-use crate::Val;
+use crate::{value::{error::Error, ty::Ty, unknown::propagate}, Val, Value};
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Function {
@@ -6,10 +6,62 @@ pub struct Function {
     pub overloads: &'static [Overload],
 }
 
+impl Function {
+    /// Calls the first overload accepting the arguments.
+    ///
+    /// Strict overloads are never handed an error or unknown argument. The call
+    /// evaluates to the union of the unknown arguments, or else the first error.
+    pub(crate) fn invoke(&self, args: Vec<Val>) -> Val {
+        let overload = self.overloads.iter().find(|o| o.accepts(&args));
+        if !matches!(overload, Some(o) if !o.strict) {
+            if let Some(v) = propagate(args.iter()) {
+                return v;
+            }
+        }
+        match overload {
+            Some(o) => (o.func)(args),
+            None => Error::no_matching_overload(self.name),
+        }
+    }
+}
+
+/// Calls the most recently added of the same-named functions that has an overload
+/// accepting the arguments.
+pub(crate) fn dispatch(functions: &[Function], args: Vec<Val>) -> Val {
+    let func = functions
+        .iter()
+        .rev()
+        .find(|f| f.overloads.iter().any(|o| o.accepts(&args)))
+        .or(functions.last());
+    match func {
+        Some(f) => f.invoke(args),
+        None => Error::unknown_function(""),
+    }
+}
+
 type Func = fn(args: Vec<Val>) -> Val;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Overload {
     pub key: &'static str,
-    pub func: Func
-}
\ No newline at end of file
+    /// Parameter types, receiver first for member calls. `Ty::Dyn` accepts any argument.
+    pub args: &'static [Ty],
+    pub result: Ty,
+    pub func: Func,
+    /// Strict overloads are skipped when an argument is an error or unknown, which is
+    /// propagated instead. Non-strict overloads receive such arguments as they are.
+    pub strict: bool,
+}
+
+impl Overload {
+    fn accepts(&self, args: &[Val]) -> bool {
+        self.args.len() == args.len()
+            && self.args.iter().zip(args).all(|(param, arg)| {
+                *param == Ty::Dyn || *param == arg.ty() || (!self.strict && is_error_or_unknown(arg))
+            })
+    }
+}
+
+fn is_error_or_unknown(val: &Val) -> bool {
+    matches!(val.ty(), Ty::Error | Ty::Unknown)
+}
@@ -5,8 +5,23 @@ mod eval;
 mod parser;
 mod function;
 mod std;
+mod time;
+mod pattern;
+mod checker;
+mod attribute;
+mod provider;
+pub mod ext;
 
 // public api
-pub use crate::program::Program;
-pub use crate::context::Context;
-pub use value::value::{Val, Value};
\ No newline at end of file
+pub use crate::program::{EvalError, Program};
+pub use crate::context::{Context, Library};
+pub use crate::checker::{CheckError, Diagnostic, Type};
+pub use crate::parser::{ExprId, Span};
+pub use crate::attribute::AttributePattern;
+pub use crate::function::{Function, Overload};
+pub use crate::provider::{ProtoTypeProvider, TypeProvider};
+pub use value::error::{Error, ErrorCode};
+pub use value::ty::Ty;
+pub use value::unknown::Unknown;
+pub use value::value::{Val, Value};
+pub use prost_reflect;
\ No newline at end of file
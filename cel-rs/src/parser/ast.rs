@@ -1,5 +1,25 @@
 use std::rc::Rc;
 
+/// Identifier of an expression node, unique within a parsed program.
+pub type ExprId = u64;
+
+/// Byte range of an expression or operator in the source text.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the 1-based line and column of the start of the span.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let prefix = &source[..self.start.min(source.len())];
+        let line = prefix.matches('\n').count() + 1;
+        let column = prefix.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        (line, column)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum RelationOp {
     LessThan,
@@ -29,9 +49,83 @@ pub enum UnaryOp {
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum Expression {
-    Arithmetic(Box<Expression>, ArithmeticOp, Box<Expression>),
-    Relation(Box<Expression>, RelationOp, Box<Expression>),
+pub struct Expression {
+    pub id: ExprId,
+    pub span: Span,
+    pub kind: ExprKind,
+}
+
+impl Expression {
+    pub fn new(start: usize, end: usize, kind: ExprKind) -> Self {
+        Self {
+            id: 0,
+            span: Span { start, end },
+            kind,
+        }
+    }
+
+    /// Numbers the nodes of the tree in pre-order, starting at 1.
+    pub fn assign_ids(&mut self) {
+        fn walk(expr: &mut Expression, next: &mut ExprId) {
+            *next += 1;
+            expr.id = *next;
+            for child in expr.children_mut() {
+                walk(child, next);
+            }
+        }
+        walk(self, &mut 0);
+    }
+
+    /// The dotted name spelled by an identifier followed by field selections, such
+    /// as `a.b.c`.
+    pub fn qualified_name(&self) -> Option<String> {
+        match &self.kind {
+            ExprKind::Ident(name) => Some(name.to_string()),
+            ExprKind::Member(operand, member) => match member.as_ref() {
+                Member::Attribute(field) => operand.qualified_name().map(|q| format!("{}.{}", q, field)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match &mut self.kind {
+            ExprKind::Arithmetic(left, _, _, right)
+            | ExprKind::Relation(left, _, _, right)
+            | ExprKind::Or(left, right)
+            | ExprKind::And(left, right) => vec![left, right],
+            ExprKind::Ternary(condition, left, right) => vec![condition, left, right],
+            ExprKind::Unary(_, operand) | ExprKind::Has(operand, _) => vec![operand],
+            ExprKind::Member(operand, member) => {
+                let mut children = vec![operand.as_mut()];
+                match member.as_mut() {
+                    Member::Attribute(_) => {}
+                    Member::FunctionCall(_, args) => children.extend(args.iter_mut()),
+                    Member::Index(index) => children.push(index),
+                    Member::Fields(fields) => children.extend(fields.iter_mut().map(|(_, v)| v)),
+                }
+                children
+            }
+            ExprKind::GlobalFunctionCall(_, args) | ExprKind::List(args) => args.iter_mut().collect(),
+            ExprKind::Map(entries) => entries.iter_mut().flat_map(|(k, v)| [k, v]).collect(),
+            ExprKind::Atom(_) | ExprKind::Ident(_) => vec![],
+            ExprKind::Comprehension(c) => vec![
+                &mut c.iter_range,
+                &mut c.accu_init,
+                &mut c.loop_condition,
+                &mut c.loop_step,
+                &mut c.result,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExprKind {
+    /// A binary operator, with the span of the operator itself.
+    Arithmetic(Box<Expression>, ArithmeticOp, Span, Box<Expression>),
+    Relation(Box<Expression>, RelationOp, Span, Box<Expression>),
 
     Ternary(Box<Expression>, Box<Expression>, Box<Expression>),
     Or(Box<Expression>, Box<Expression>),
@@ -47,6 +141,31 @@ pub enum Expression {
 
     Atom(Atom),
     Ident(Rc<String>),
+
+    /// `has(operand.field)`, which tests for the field instead of selecting it.
+    Has(Box<Expression>, Rc<String>),
+
+    /// A macro call expanded into a loop; see [`Comprehension`].
+    Comprehension(Box<Comprehension>),
+}
+
+/// Folds `accu_var` over the elements of `iter_range`, bound to `iter_var`, while
+/// `loop_condition` holds, then evaluates `result`. Map ranges iterate their keys.
+///
+/// With a second iteration variable, `iter_var` is bound to the index of a list
+/// element or to a map key, and `iter_var2` to the element or the map value.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comprehension {
+    pub iter_var: Rc<String>,
+    pub iter_var2: Option<Rc<String>>,
+    pub iter_range: Expression,
+    pub accu_var: Rc<String>,
+    pub accu_init: Expression,
+    pub loop_condition: Expression,
+    pub loop_step: Expression,
+    pub result: Expression,
+    /// The macro call the comprehension was expanded from, kept to print it back.
+    pub source: Box<Expression>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -0,0 +1,290 @@
+//! Expansion of macro calls, such as `list.all(e, e > 0)`, into comprehensions
+//! and plain calls. Expansion happens once, right after parsing.
+
+use std::rc::Rc;
+
+use super::ast::{ArithmeticOp, Atom, Comprehension, ExprKind, Expression, Member, RelationOp, Span, UnaryOp};
+
+/// Name of the accumulator of comprehensions that build a result.
+const RESULT: &str = "@result";
+
+/// Rewrites every macro call in the expression, innermost first.
+pub fn expand(mut expr: Expression) -> Result<Expression, String> {
+    for child in expr.children_mut() {
+        let placeholder = Expression::new(0, 0, ExprKind::Atom(Atom::Null));
+        *child = expand(std::mem::replace(child, placeholder))?;
+    }
+    let expanded = match &expr.kind {
+        ExprKind::GlobalFunctionCall(name, args) => expand_global_call(&expr, name, args)?,
+        ExprKind::Member(target, member) => match member.as_ref() {
+            Member::FunctionCall(name, args) => expand_receiver_call(&expr, target, name, args)?,
+            _ => None,
+        },
+        _ => None,
+    };
+    Ok(expanded.unwrap_or(expr))
+}
+
+fn expand_global_call(call: &Expression, name: &str, args: &[Expression]) -> Result<Option<Expression>, String> {
+    match (name, args) {
+        ("has", [arg]) => match &arg.kind {
+            ExprKind::Member(operand, member) => match member.as_ref() {
+                Member::Attribute(field) => {
+                    let kind = ExprKind::Has(operand.clone(), field.clone());
+                    Ok(Some(Expression { kind, ..call.clone() }))
+                }
+                _ => Err("invalid argument to has() macro".to_string()),
+            },
+            _ => Err("invalid argument to has() macro".to_string()),
+        },
+        _ => Ok(None),
+    }
+}
+
+fn expand_receiver_call(
+    call: &Expression,
+    target: &Expression,
+    name: &str,
+    args: &[Expression],
+) -> Result<Option<Expression>, String> {
+    let m = Macro { call };
+    if let ExprKind::Ident(namespace) = &target.kind {
+        if let ("cel", "bind", [var, init, body]) = (namespace.as_str(), name, args) {
+            let var = m.var_name("cel.bind", var)?;
+            return Ok(Some(m.bind(var, init.clone(), body.clone())));
+        }
+    }
+    let range = target.clone();
+    Ok(Some(match (name, args) {
+        ("all", [var, predicate]) => m.all(range, m.var_name(name, var)?, None, predicate.clone()),
+        ("all", [var, var2, predicate]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            m.all(range, var, Some(var2), predicate.clone())
+        }
+        ("exists", [var, predicate]) => m.exists(range, m.var_name(name, var)?, None, predicate.clone()),
+        ("exists", [var, var2, predicate]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            m.exists(range, var, Some(var2), predicate.clone())
+        }
+        ("exists_one" | "existsOne", [var, predicate]) => {
+            m.exists_one(range, m.var_name(name, var)?, None, predicate.clone())
+        }
+        ("exists_one" | "existsOne", [var, var2, predicate]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            m.exists_one(range, var, Some(var2), predicate.clone())
+        }
+        ("map", [var, transform]) => m.map(range, m.var_name(name, var)?, None, None, transform.clone()),
+        ("map", [var, filter, transform]) => {
+            m.map(range, m.var_name(name, var)?, None, Some(filter.clone()), transform.clone())
+        }
+        ("filter", [var, predicate]) => m.filter(range, m.var_name(name, var)?, predicate.clone()),
+        ("transformList", [var, var2, transform]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            m.map(range, var, Some(var2), None, transform.clone())
+        }
+        ("transformList", [var, var2, filter, transform]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            m.map(range, var, Some(var2), Some(filter.clone()), transform.clone())
+        }
+        ("transformMap", [var, var2, transform]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            let entry = vec![m.ident(var), transform.clone()];
+            m.transform_map(range, var, var2, None, entry)
+        }
+        ("transformMap", [var, var2, filter, transform]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            let entry = vec![m.ident(var), transform.clone()];
+            m.transform_map(range, var, var2, Some(filter.clone()), entry)
+        }
+        ("transformMapEntry", [var, var2, transform]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            m.transform_map(range, var, var2, None, vec![transform.clone()])
+        }
+        ("transformMapEntry", [var, var2, filter, transform]) => {
+            let (var, var2) = m.var_names(name, var, var2)?;
+            m.transform_map(range, var, var2, Some(filter.clone()), vec![transform.clone()])
+        }
+        ("sortBy", [var, key]) => {
+            let var = m.var_name(name, var)?;
+            m.sort_by(target, var, key)
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// Builds the expansion of one macro call. Generated nodes take the call's span.
+struct Macro<'a> {
+    call: &'a Expression,
+}
+
+impl Macro<'_> {
+    fn span(&self) -> Span {
+        self.call.span
+    }
+
+    fn node(&self, kind: ExprKind) -> Expression {
+        Expression::new(self.span().start, self.span().end, kind)
+    }
+
+    fn ident(&self, name: &str) -> Expression {
+        self.node(ExprKind::Ident(Rc::new(name.to_string())))
+    }
+
+    fn bool(&self, b: bool) -> Expression {
+        self.node(ExprKind::Atom(Atom::Bool(b)))
+    }
+
+    fn int(&self, i: i64) -> Expression {
+        self.node(ExprKind::Atom(Atom::Int(i)))
+    }
+
+    fn global_call(&self, name: &str, args: Vec<Expression>) -> Expression {
+        self.node(ExprKind::GlobalFunctionCall(Rc::new(name.to_string()), args))
+    }
+
+    fn add(&self, left: Expression, right: Expression) -> Expression {
+        self.node(ExprKind::Arithmetic(Box::new(left), ArithmeticOp::Add, self.span(), Box::new(right)))
+    }
+
+    fn ternary(&self, condition: Expression, left: Expression, right: Expression) -> Expression {
+        self.node(ExprKind::Ternary(Box::new(condition), Box::new(left), Box::new(right)))
+    }
+
+    /// `@not_strictly_false(e)`, which keeps a loop going past errors.
+    fn not_strictly_false(&self, e: Expression) -> Expression {
+        self.global_call("@not_strictly_false", vec![e])
+    }
+
+    fn var_name<'e>(&self, name: &str, var: &'e Expression) -> Result<&'e Rc<String>, String> {
+        match &var.kind {
+            ExprKind::Ident(v) if !v.starts_with('.') => Ok(v),
+            _ => Err(format!("{}() variable name must be a simple identifier", name)),
+        }
+    }
+
+    fn var_names<'e>(
+        &self,
+        name: &str,
+        var: &'e Expression,
+        var2: &'e Expression,
+    ) -> Result<(&'e Rc<String>, &'e Rc<String>), String> {
+        let (var, var2) = (self.var_name(name, var)?, self.var_name(name, var2)?);
+        if var == var2 {
+            return Err(format!("{}() second variable must be different from the first variable", name));
+        }
+        Ok((var, var2))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn comprehension(
+        &self,
+        iter_var: &str,
+        iter_var2: Option<&str>,
+        iter_range: Expression,
+        accu_var: &str,
+        accu_init: Expression,
+        loop_condition: Expression,
+        loop_step: Expression,
+        result: Expression,
+    ) -> Expression {
+        self.node(ExprKind::Comprehension(Box::new(Comprehension {
+            iter_var: Rc::new(iter_var.to_string()),
+            iter_var2: iter_var2.map(|v| Rc::new(v.to_string())),
+            iter_range,
+            accu_var: Rc::new(accu_var.to_string()),
+            accu_init,
+            loop_condition,
+            loop_step,
+            result,
+            source: Box::new(self.call.clone()),
+        })))
+    }
+
+    /// Evaluates `init` once and makes it available as `name` within `body`.
+    fn bind(&self, name: &str, init: Expression, body: Expression) -> Expression {
+        let empty = self.node(ExprKind::List(vec![]));
+        self.comprehension("#unused", None, empty, name, init, self.bool(false), self.ident(name), body)
+    }
+
+    /// `range.all(var, predicate)`
+    fn all(&self, range: Expression, var: &str, var2: Option<&str>, predicate: Expression) -> Expression {
+        let condition = self.not_strictly_false(self.ident(RESULT));
+        let step = self.node(ExprKind::And(Box::new(self.ident(RESULT)), Box::new(predicate)));
+        self.comprehension(var, var2, range, RESULT, self.bool(true), condition, step, self.ident(RESULT))
+    }
+
+    /// `range.exists(var, predicate)`
+    fn exists(&self, range: Expression, var: &str, var2: Option<&str>, predicate: Expression) -> Expression {
+        let not_found = self.node(ExprKind::Unary(UnaryOp::Not, Box::new(self.ident(RESULT))));
+        let condition = self.not_strictly_false(not_found);
+        let step = self.node(ExprKind::Or(Box::new(self.ident(RESULT)), Box::new(predicate)));
+        self.comprehension(var, var2, range, RESULT, self.bool(false), condition, step, self.ident(RESULT))
+    }
+
+    /// `range.exists_one(var, predicate)`
+    fn exists_one(&self, range: Expression, var: &str, var2: Option<&str>, predicate: Expression) -> Expression {
+        let count = self.add(self.ident(RESULT), self.int(1));
+        let step = self.ternary(predicate, count, self.ident(RESULT));
+        let result = self.node(ExprKind::Relation(
+            Box::new(self.ident(RESULT)),
+            RelationOp::Equals,
+            self.span(),
+            Box::new(self.int(1)),
+        ));
+        self.comprehension(var, var2, range, RESULT, self.int(0), self.bool(true), step, result)
+    }
+
+    /// `range.map(var, transform)`, keeping only the elements passing the filter
+    /// if there is one.
+    fn map(
+        &self,
+        range: Expression,
+        var: &str,
+        var2: Option<&str>,
+        filter: Option<Expression>,
+        transform: Expression,
+    ) -> Expression {
+        let mut step = self.add(self.ident(RESULT), self.node(ExprKind::List(vec![transform])));
+        if let Some(filter) = filter {
+            step = self.ternary(filter, step, self.ident(RESULT));
+        }
+        let init = self.node(ExprKind::List(vec![]));
+        self.comprehension(var, var2, range, RESULT, init, self.bool(true), step, self.ident(RESULT))
+    }
+
+    /// `range.filter(var, predicate)`
+    fn filter(&self, range: Expression, var: &str, predicate: Expression) -> Expression {
+        let kept = self.add(self.ident(RESULT), self.node(ExprKind::List(vec![self.ident(var)])));
+        let step = self.ternary(predicate, kept, self.ident(RESULT));
+        let init = self.node(ExprKind::List(vec![]));
+        self.comprehension(var, None, range, RESULT, init, self.bool(true), step, self.ident(RESULT))
+    }
+
+    /// Builds a map by inserting, for each element, either a key and a value or a
+    /// single-entry map, as given by `entry`.
+    fn transform_map(
+        &self,
+        range: Expression,
+        var: &str,
+        var2: &str,
+        filter: Option<Expression>,
+        entry: Vec<Expression>,
+    ) -> Expression {
+        let mut args = vec![self.ident(RESULT)];
+        args.extend(entry);
+        let mut step = self.global_call("@mapInsert", args);
+        if let Some(filter) = filter {
+            step = self.ternary(filter, step, self.ident(RESULT));
+        }
+        let init = self.node(ExprKind::Map(vec![]));
+        self.comprehension(var, Some(var2), range, RESULT, init, self.bool(true), step, self.ident(RESULT))
+    }
+
+    /// `target.sortBy(var, key)` sorts by the keys computed for each element.
+    fn sort_by(&self, target: &Expression, var: &str, key: &Expression) -> Expression {
+        const INPUT: &str = "@__sortBy_input__";
+        let keys = self.map(self.ident(INPUT), var, None, None, key.clone());
+        let sorted = self.global_call("@sortByAssociatedKeys", vec![self.ident(INPUT), keys]);
+        self.bind(INPUT, target.clone(), sorted)
+    }
+}
@@ -1,7 +1,9 @@
 use lalrpop_util::lalrpop_mod;
 
 pub mod ast;
+pub mod macros;
 pub mod parse;
+mod unparse;
 pub use ast::*;
 
 lalrpop_mod!(
@@ -2,4 +2,39 @@ use unescape::unescape;
 
 pub fn parse_str(str: &str) -> String {
     unescape(str).unwrap_or(String::new())
-}
\ No newline at end of file
+}
+
+/// Unescapes the body of a bytes literal. Unlike in strings, `\x` and octal
+/// escapes stand for a single byte, so `b'\xff'` is one byte rather than the
+/// UTF-8 encoding of U+00FF.
+pub fn parse_bytes(str: &str) -> Vec<u8> {
+    unescape_bytes(str).unwrap_or_default()
+}
+
+fn unescape_bytes(str: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(str.len());
+    let mut rest = str;
+    while let Some(i) = rest.find('\\') {
+        bytes.extend_from_slice(&rest.as_bytes()[..i]);
+        let escape = &rest[i + 1..];
+        let len = match escape.as_bytes().first()? {
+            b'x' | b'X' => {
+                bytes.push(u8::from_str_radix(escape.get(1..3)?, 16).ok()?);
+                3
+            }
+            b'0'..=b'3' => {
+                bytes.push(u8::from_str_radix(escape.get(..3)?, 8).ok()?);
+                3
+            }
+            c => {
+                let len = if *c == b'u' { 5 } else { 1 };
+                let c = unescape(&format!("\\{}", escape.get(..len)?))?;
+                bytes.extend_from_slice(c.as_bytes());
+                len
+            }
+        };
+        rest = &escape[len..];
+    }
+    bytes.extend_from_slice(rest.as_bytes());
+    Some(bytes)
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+
+use super::ast::{ArithmeticOp, Atom, ExprKind, Expression, Member, RelationOp, UnaryOp};
+
+/// Writes an expression back out as CEL source.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Arithmetic(left, op, _, right) => {
+                let op = match op {
+                    ArithmeticOp::Add => "+",
+                    ArithmeticOp::Subtract => "-",
+                    ArithmeticOp::Divide => "/",
+                    ArithmeticOp::Multiply => "*",
+                    ArithmeticOp::Modulus => "%",
+                };
+                write!(f, "{} {} {}", Operand(left), op, Operand(right))
+            }
+            ExprKind::Relation(left, op, _, right) => {
+                let op = match op {
+                    RelationOp::LessThan => "<",
+                    RelationOp::LessThanEq => "<=",
+                    RelationOp::GreaterThan => ">",
+                    RelationOp::GreaterThanEq => ">=",
+                    RelationOp::Equals => "==",
+                    RelationOp::NotEquals => "!=",
+                    RelationOp::In => "in",
+                };
+                write!(f, "{} {} {}", Operand(left), op, Operand(right))
+            }
+            ExprKind::Ternary(condition, left, right) => {
+                write!(f, "{} ? {} : {}", Operand(condition), Operand(left), Primary(right))
+            }
+            ExprKind::Or(left, right) => write!(f, "{} || {}", Operand(left), Operand(right)),
+            ExprKind::And(left, right) => write!(f, "{} && {}", Operand(left), Operand(right)),
+            ExprKind::Unary(op, operand) => {
+                let op = match op {
+                    UnaryOp::Not => "!",
+                    UnaryOp::DoubleNot => "!!",
+                    UnaryOp::Minus => "-",
+                    UnaryOp::DoubleMinus => "--",
+                };
+                write!(f, "{}{}", op, Operand(operand))
+            }
+            ExprKind::Member(operand, member) => {
+                write!(f, "{}", Operand(operand))?;
+                match member.as_ref() {
+                    Member::Attribute(name) => write!(f, ".{}", name),
+                    Member::FunctionCall(name, args) => write!(f, ".{}({})", name, List(args)),
+                    Member::Index(index) => write!(f, "[{}]", index),
+                    Member::Fields(fields) => {
+                        write!(f, "{{")?;
+                        for (i, (name, value)) in fields.iter().enumerate() {
+                            if i > 0 {
+                                write!(f, ", ")?;
+                            }
+                            write!(f, "{}: {}", name, value)?;
+                        }
+                        write!(f, "}}")
+                    }
+                }
+            }
+            ExprKind::GlobalFunctionCall(name, args) => write!(f, "{}({})", name, List(args)),
+            ExprKind::List(elems) => write!(f, "[{}]", List(elems)),
+            ExprKind::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            ExprKind::Atom(atom) => write!(f, "{}", atom),
+            ExprKind::Ident(name) => write!(f, "{}", name),
+            ExprKind::Has(operand, field) => write!(f, "has({}.{})", Operand(operand), field),
+            ExprKind::Comprehension(c) => write!(f, "{}", c.source),
+        }
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Atom::Int(i) => write!(f, "{}", i),
+            Atom::UInt(u) => write!(f, "{}u", u),
+            Atom::Float(d) => write!(f, "{:?}", d),
+            Atom::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Atom::Bytes(bytes) => {
+                write!(f, "b'")?;
+                for b in bytes.iter() {
+                    match b {
+                        b'\'' => write!(f, "\\'")?,
+                        b'\\' => write!(f, "\\\\")?,
+                        0x20..=0x7e => write!(f, "{}", *b as char)?,
+                        _ => write!(f, "\\x{:02x}", b)?,
+                    }
+                }
+                write!(f, "'")
+            }
+            Atom::Bool(b) => write!(f, "{}", b),
+            Atom::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// Comma separated expressions.
+struct List<'a>(&'a [Expression]);
+
+impl fmt::Display for List<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, expr) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", expr)?;
+        }
+        Ok(())
+    }
+}
+
+/// Operand of an operator or selection; nested operators are parenthesized.
+struct Operand<'a>(&'a Expression);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.kind {
+            ExprKind::Arithmetic(..)
+            | ExprKind::Relation(..)
+            | ExprKind::Ternary(..)
+            | ExprKind::Or(..)
+            | ExprKind::And(..)
+            | ExprKind::Unary(..) => write!(f, "({})", self.0),
+            _ => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// Operand the grammar only accepts as a primary expression.
+struct Primary<'a>(&'a Expression);
+
+impl fmt::Display for Primary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.kind {
+            ExprKind::Atom(_)
+            | ExprKind::Ident(_)
+            | ExprKind::GlobalFunctionCall(..)
+            | ExprKind::List(_)
+            | ExprKind::Map(_) => write!(f, "{}", self.0),
+            _ => write!(f, "({})", self.0),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Regular expressions compiled at most once per program.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{value::error::Error, Val};
+
+/// Upper bound on the compiled size of a pattern, so that an expression can't
+/// make evaluation allocate without limit.
+const MAX_COMPILED_SIZE: usize = 1 << 20;
+
+/// How many patterns a program keeps compiled. Patterns computed at evaluation
+/// time can be unbounded in number, so those beyond it are compiled on every use.
+const MAX_CACHED_PATTERNS: usize = 64;
+
+/// The patterns a program has compiled, along with why those that failed did.
+#[derive(Default)]
+pub(crate) struct RegexCache(pub(crate) RefCell<HashMap<String, Result<Regex, String>>>);
+
+impl RegexCache {
+    fn compile(&self, pattern: &str) -> Result<Regex, String> {
+        let mut entries = self.0.borrow_mut();
+        if let Some(compiled) = entries.get(pattern) {
+            return compiled.clone();
+        }
+        let compiled = build(pattern);
+        if entries.len() < MAX_CACHED_PATTERNS {
+            entries.insert(pattern.to_string(), compiled.clone());
+        }
+        compiled
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<RegexCache>>> = const { RefCell::new(None) };
+}
+
+/// Puts the cache that was current back when dropped, so that it is restored even
+/// if evaluation unwinds.
+struct Restore(Option<Rc<RegexCache>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|c| c.replace(self.0.take()));
+    }
+}
+
+/// Runs `f` with the cache used by [`compile`].
+pub(crate) fn with_cache<R>(cache: &Rc<RegexCache>, f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(CURRENT.with(|c| c.replace(Some(cache.clone()))));
+    f()
+}
+
+/// Compiles an RE2 pattern, or returns the error value for an invalid one.
+pub(crate) fn compile(pattern: &str) -> Result<Regex, Val> {
+    let cache = CURRENT.with(|c| c.borrow().clone());
+    let compiled = match &cache {
+        Some(cache) => cache.compile(pattern),
+        None => build(pattern),
+    };
+    compiled.map_err(|e| Error::invalid_argument(format!("invalid regular expression '{}': {}", pattern, e)))
+}
+
+fn build(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .size_limit(MAX_COMPILED_SIZE)
+        .build()
+        .map_err(|e| e.to_string())
+}
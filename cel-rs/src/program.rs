@@ -1,15 +1,21 @@
+use crate::checker::{CheckError, Checker, Type};
 use crate::context::Context;
 use crate::eval::Eval;
 use crate::parser::cel::ExpressionParser;
-use crate::parser::Expression;
+use crate::parser::{macros, Expression};
+use crate::pattern::{self, RegexCache};
+use crate::value::error::{Error, ErrorCode};
 use crate::value::value::Val;
 use crate::value::{value::Value};
 
 use std::fmt;
+use std::rc::Rc;
 use std::result::Result;
 
 pub struct Program {
+    source: String,
     expr: Expression,
+    regexes: Rc<RegexCache>,
 }
 
 #[derive(Debug)]
@@ -23,17 +29,73 @@ impl fmt::Display for ParseError {
     }
 }
 
+/// An error value produced by evaluation, together with the source it was raised in.
+#[derive(Debug)]
+pub struct EvalError {
+    source: String,
+    error: Error,
+}
+
+impl EvalError {
+    pub fn code(&self) -> ErrorCode {
+        self.error.code()
+    }
+
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> fmt::Result {
+        match self.error.span() {
+            Some(span) => {
+                let (line, column) = span.location(&self.source);
+                write!(f, "<input>:{}:{}: {}", line, column, self.error.message())
+            }
+            None => write!(f, "{}", self.error.message()),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
 impl Program {
     pub fn new(source: &str) -> Result<Program, ParseError> {
         match ExpressionParser::new().parse(source) {
-            Ok(expr) => Ok(Program { expr }),
+            Ok(expr) => {
+                let mut expr = macros::expand(expr).map_err(|message| ParseError { message })?;
+                expr.assign_ids();
+                Ok(Program {
+                    source: source.to_string(),
+                    expr,
+                    regexes: Default::default(),
+                })
+            }
             Err(e) => Err(ParseError {
                 message: format!("{}", e),
             }),
         }
     }
 
-    pub fn execute(self, context: &mut Context) -> bool {
+    /// Type-checks the program against the variables and functions known to the context.
+    pub fn check(&self, context: &Context) -> Result<Type, CheckError> {
+        Checker::new(context)
+            .check(&self.expr)
+            .map_err(|diagnostics| CheckError::new(&self.source, diagnostics))
+    }
+
+    pub fn execute(&self, context: &mut Context) -> bool {
         self.eval(context)
             .to_bool()
             .as_bool()
@@ -41,9 +103,30 @@ impl Program {
             .to_owned()
     }
 
-    pub fn eval(self, context: &mut Context) -> Val {
+    pub fn eval(&self, context: &mut Context) -> Val {
         let e = Eval::default();
-        e.eval(self.expr, context)
+        pattern::with_cache(&self.regexes, || e.eval(&self.expr, context))
+    }
+
+    /// Like [`Program::eval`], but surfaces error values as an [`EvalError`].
+    pub fn try_eval(&self, context: &mut Context) -> Result<Val, EvalError> {
+        let val = self.eval(context);
+        match val.as_error() {
+            Some(error) => Err(EvalError {
+                source: self.source.clone(),
+                error: error.clone(),
+            }),
+            None => Ok(val),
+        }
+    }
+
+    /// Partially evaluates the program, returning a program made of the parts that
+    /// still depend on attributes the context marks as unknown. Parts that could be
+    /// evaluated are folded into literals.
+    pub fn residual(&self, context: &mut Context) -> Result<Program, ParseError> {
+        let e = Eval::tracking();
+        pattern::with_cache(&self.regexes, || e.eval(&self.expr, context));
+        Program::new(&e.residual(&self.expr).to_string())
     }
 }
 
@@ -67,6 +150,10 @@ pub mod tests {
         }};
     }
 
+    fn eval_program_str(expr: &str) -> Val {
+        crate::program::Program::new(expr).unwrap().eval(&mut crate::context::Context::default())
+    }
+
     #[test]
     fn test_bool() {
         let mut ctx = program::Context::default();
@@ -89,6 +176,8 @@ pub mod tests {
     #[test]
     fn test_bytes() {
         assert_eq!(eval_program!(r#"b''"#), Val::new_bytes(vec![].into()));
+        assert_eq!(eval_program!(r#"b'\xff\377a\n'"#), Val::new_bytes(vec![0xff, 0xff, b'a', b'\n'].into()));
+        assert_eq!(eval_program!(r#"b'\u00ff'"#), Val::new_bytes(vec![0xc3, 0xbf].into()));
     }
 
 
@@ -126,6 +215,1325 @@ pub mod tests {
         assert_eq!(value, expected_value, r#""{:?}" did not match "{:?}""#, value, expected_value);
     }
 
+    #[test]
+    fn check_undeclared_reference() {
+        let mut ctx = crate::Context::default();
+        ctx.add_variable("user", Val::new_string("alice"));
+        let err = crate::Program::new("[usr == 'alice']").unwrap().check(&ctx).unwrap_err();
+        assert_eq!(err.diagnostics().len(), 1);
+        assert_eq!(
+            err.to_string(),
+            "ERROR: <input>:1:2: undeclared reference to 'usr' (did you mean 'user'?)\n | [usr == 'alice']\n | .^"
+        );
+    }
+
+    #[test]
+    fn check_no_matching_overload() {
+        let ctx = crate::Context::default();
+        let err = crate::Program::new("1 + 'a'").unwrap().check(&ctx).unwrap_err();
+        assert_eq!(
+            err.diagnostics()[0].message,
+            "found no matching overload for '_+_' applied to '(int, string)'"
+        );
+        assert_eq!(err.diagnostics()[0].span, crate::Span { start: 2, end: 3 });
+        assert!(err.to_string().starts_with("ERROR: <input>:1:3:"), "{}", err);
+        let err = crate::Program::new("[1] < 2").unwrap().check(&ctx).unwrap_err();
+        assert_eq!(err.diagnostics()[0].span, crate::Span { start: 4, end: 5 });
+        let err = crate::Program::new("dyn(1, 2)").unwrap().check(&ctx).unwrap_err();
+        assert_eq!(
+            err.diagnostics()[0].message,
+            "found no matching overload for 'dyn' applied to '(int, int)'"
+        );
+    }
+
+    #[test]
+    fn check_field_selection() {
+        let mut ctx = crate::Context::default();
+        ctx.declare_variable("limits", crate::Type::map(crate::Type::String, crate::Type::Int));
+        ctx.add_variable("count", Val::new_int(1));
+        let program = crate::Program::new("limits.max >= count").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::Bool);
+        let err = crate::Program::new("count.max").unwrap().check(&ctx).unwrap_err();
+        assert_eq!(
+            err.diagnostics()[0].message,
+            "unexpected type for field 'max': type 'int' does not support field selection"
+        );
+    }
+
+    #[test]
+    fn check_aggregate_types() {
+        let ctx = crate::Context::default();
+        let check = |expr: &str| crate::Program::new(expr).unwrap().check(&ctx).unwrap();
+        assert_eq!(check("[1, 2]"), crate::Type::list(crate::Type::Int));
+        assert_eq!(check("[1, 'a']"), crate::Type::list(crate::Type::Dyn));
+        assert_eq!(check("{'a': 1}"), crate::Type::map(crate::Type::String, crate::Type::Int));
+        assert_eq!(check("[1, 2][0]"), crate::Type::Int);
+    }
+
+    #[test]
+    fn check_numeric_comparisons() {
+        let mut ctx = crate::Context::default();
+        for l in ["1", "2u", "1.5"] {
+            for r in ["1", "2u", "1.5"] {
+                let program = crate::Program::new(&format!("{} < {}", l, r)).unwrap();
+                assert_eq!(program.check(&ctx).unwrap(), crate::Type::Bool);
+                assert!(program.eval(&mut ctx).as_bool().is_some(), "{} < {}", l, r);
+            }
+        }
+    }
+
+    #[test]
+    fn check_homogeneous_aggregate_literals() {
+        let mut ctx = crate::Context::default();
+        let check = |expr: &str, ctx: &crate::Context| crate::Program::new(expr).unwrap().check(ctx);
+        assert!(check("[1, 'a']", &ctx).is_ok());
+
+        ctx.homogeneous_aggregate_literals(true);
+        let err = check("[1, 'a']", &ctx).unwrap_err();
+        assert_eq!(err.diagnostics()[0].message, "expected type 'int' but found 'string'");
+        assert_eq!(err.diagnostics()[0].span, crate::Span { start: 4, end: 7 });
+        assert_eq!(check("{'a': 1, 2: 'b'}", &ctx).unwrap_err().diagnostics().len(), 2);
+        assert_eq!(check("[[1], ['a']]", &ctx).unwrap_err().diagnostics().len(), 1);
+
+        assert_eq!(check("[1, dyn('a')]", &ctx).unwrap(), crate::Type::list(crate::Type::Dyn));
+        assert!(check("{'a': dyn(1), dyn(2): 'b'}", &ctx).is_ok());
+        assert_eq!(check("[1, 2]", &ctx).unwrap(), crate::Type::list(crate::Type::Int));
+    }
+
+    #[test]
+    fn test_strict_functions() {
+        use crate::function::{Function, Overload};
+        use crate::value::ty::Ty;
+        use crate::Value;
+
+        fn describe(args: Vec<Val>) -> Val {
+            Val::new_string(format!("{:?}", args[0].ty()))
+        }
+
+        let mut ctx = crate::Context::default();
+        ctx.add_function("strict_describe", Function {
+            name: "strict_describe",
+            overloads: &[Overload { key: "strict_describe", args: &[Ty::Dyn], result: Ty::String, func: describe, strict: true }],
+        });
+        ctx.add_function("describe", Function {
+            name: "describe",
+            overloads: &[Overload { key: "describe", args: &[Ty::Dyn], result: Ty::String, func: describe, strict: false }],
+        });
+
+        assert_eq!(eval_program!(r#"strict_describe(1)"#, &mut ctx), Val::new_string("Int"));
+        assert_eq!(
+            eval_program!(r#"strict_describe(missing)"#, &mut ctx),
+            Val::new_error("unknown variable missing".to_string())
+        );
+        assert_eq!(eval_program!(r#"describe(missing)"#, &mut ctx), Val::new_string("Error"));
+        assert_eq!(
+            eval_program!(r#"dyn(1, 2)"#, &mut ctx),
+            Val::new_error("no matching overload for 'dyn'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut ctx = crate::Context::default();
+        ctx.add_variable("x", Val::new_int(3));
+        assert_eq!(eval_program!(r#"x + 2"#, &mut ctx), Val::new_int(5));
+        assert_eq!(eval_program!(r#"-x"#, &mut ctx), Val::new_int(-3));
+        assert_eq!(eval_program!(r#"7u % 4u"#), Val::new_uint(3));
+        assert_eq!(eval_program!(r#"1.5 * 2.0"#), Val::new_double(3.0));
+        assert_eq!(eval_program!(r#"'ab' + 'c'"#), Val::new_string("abc"));
+        assert_eq!(eval_program!(r#"[1] + [2] == [1, 2]"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"1 / 0"#), Val::new_error("division by zero".to_string()));
+        assert_eq!(
+            eval_program!(r#"9223372036854775807 + 1"#),
+            Val::new_error("integer overflow".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"1 + 1u"#),
+            Val::new_error("no matching overload for '_+_'".to_string())
+        );
+        assert_eq!(eval_program!(r#"2 in [1, 2]"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"'b' in {'a': 1}"#), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"[1, 2][1]"#), Val::new_int(2));
+        assert_eq!(eval_program!(r#"{'a': 1}.a"#), Val::new_int(1));
+        assert_eq!(eval_program!(r#"{'a': 1}['b']"#), Val::new_error("no such key: b".to_string()));
+        assert_eq!(eval_program!(r#"true ? 1 : 2"#), Val::new_int(1));
+        assert_eq!(eval_program!(r#"!true || false"#), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"missing && false"#), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"true || missing"#), Val::new_bool(true));
+    }
+
+    #[test]
+    fn test_unknowns() {
+        use crate::Value;
+
+        let mut ctx = crate::Context::default();
+        ctx.add_variable(
+            "request",
+            Val::new_map(std::collections::HashMap::from([(Val::new_string("method"), Val::new_string("GET"))]).into()),
+        );
+        ctx.add_attribute_pattern(crate::AttributePattern::new("request.auth"));
+
+        assert_eq!(eval_program!(r#"request.method == 'GET'"#, &mut ctx), Val::new_bool(true));
+
+        let unknown = eval_program!(r#"request.auth.claims"#, &mut ctx);
+        assert_eq!(unknown.as_unknown().unwrap().ids().collect::<Vec<_>>(), vec![1]);
+        let unknown = eval_program!(r#"[request.auth.a, request['auth'].b]"#, &mut ctx);
+        assert_eq!(unknown.as_unknown().unwrap().ids().collect::<Vec<_>>(), vec![2, 5]);
+
+        assert_eq!(eval_program!(r#"request.auth.sub == 'x' && false"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"request.auth.sub == 'x' || true"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(
+            eval_program!(r#"request.auth.sub == 'x' && request.method == 'GET'"#, &mut ctx).ty(),
+            crate::Ty::Unknown
+        );
+    }
+
+    #[test]
+    fn test_residual() {
+        let mut ctx = crate::Context::default();
+        ctx.add_variable("limit", Val::new_int(5));
+        ctx.add_variable(
+            "request",
+            Val::new_map(std::collections::HashMap::from([(Val::new_string("method"), Val::new_string("GET"))]).into()),
+        );
+        ctx.add_attribute_pattern(crate::AttributePattern::new("request.auth.*"));
+
+        let residual = |expr: &str, ctx: &mut crate::Context| crate::Program::new(expr).unwrap().residual(ctx).unwrap().to_string();
+        assert_eq!(
+            residual(r#"request.method == 'POST' || request.auth.sub == 'admin'"#, &mut ctx),
+            r#"request.auth.sub == "admin""#
+        );
+        assert_eq!(
+            residual(r#"request.method == 'GET' && request.auth.level > limit"#, &mut ctx),
+            r#"request.auth.level > 5"#
+        );
+        assert_eq!(
+            residual(r#"request.auth.admin ? [limit, 1] : limit"#, &mut ctx),
+            r#"request.auth.admin ? [5, 1] : 5"#
+        );
+        assert_eq!(residual(r#"request.method + '!'"#, &mut ctx), r#""GET!""#);
+
+        ctx.add_variable("data", Val::new_bytes(vec![0x80, 0xff, b'\''].into()));
+        let bytes = residual(r#"request.auth.data == data"#, &mut ctx);
+        assert_eq!(bytes, r#"request.auth.data == b'\x80\xff\''"#);
+        let literal = crate::Program::new(&bytes["request.auth.data == ".len()..]).unwrap();
+        assert_eq!(literal.eval(&mut ctx), Val::new_bytes(vec![0x80, 0xff, b'\''].into()));
+    }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(eval_program!(r#"size('héllo')"#), Val::new_int(5));
+        assert_eq!(eval_program!(r#"'héllo'.size()"#), Val::new_int(5));
+        assert_eq!(eval_program!(r#"size(b'hé')"#), Val::new_int(3));
+        assert_eq!(eval_program!(r#"[1, 2, 3].size()"#), Val::new_int(3));
+        assert_eq!(eval_program!(r#"size({'a': 1})"#), Val::new_int(1));
+        assert_eq!(
+            eval_program!(r#"size(1)"#),
+            Val::new_error("no matching overload for 'size'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_string_functions() {
+        assert_eq!(eval_program!(r#"'foobar'.contains('oba')"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"'foobar'.startsWith('foo')"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"'foobar'.endsWith('foo')"#), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"'foobar'.matches('^f.*r$')"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"'foobar'.matches('o+b')"#), Val::new_bool(true));
+        assert_eq!(
+            eval_program!(r#"'foobar'.matches('(')"#).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(
+            eval_program!(r#"timestamp('2024-01-01T00:00:00Z') + duration('1h30m')"#),
+            eval_program!(r#"timestamp('2024-01-01T01:30:00Z')"#)
+        );
+        assert_eq!(
+            eval_program!(r#"timestamp('2024-01-01T00:00:00.5+01:00') - timestamp('2023-12-31T23:00:00Z')"#),
+            Val::new_duration(0, 500_000_000)
+        );
+        assert_eq!(eval_program!(r#"timestamp(1) < timestamp(2)"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"duration('-1.5s') < duration('0')"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"string(duration('1h30m'))"#), Val::new_string("5400s"));
+        assert_eq!(eval_program!(r#"string(-duration('1.25s'))"#), Val::new_string("-1.25s"));
+        assert_eq!(eval_program!(r#"string(duration('87660000h'))"#), Val::new_string("315576000000s"));
+        assert_eq!(eval_program!(r#"duration('-3000000h').getHours()"#), Val::new_int(-3000000));
+        assert_eq!(
+            eval_program!(r#"string(timestamp('2009-02-13T23:31:30.120Z'))"#),
+            Val::new_string("2009-02-13T23:31:30.12Z")
+        );
+        assert_eq!(eval_program!(r#"int(timestamp('2009-02-13T23:31:30Z'))"#), Val::new_int(1234567890));
+
+        let ts = r#"timestamp('2024-03-10T13:45:30.250Z')"#;
+        let accessor = |f: &str| eval_program_str(&format!("{}.{}()", ts, f));
+        assert_eq!(accessor("getFullYear"), Val::new_int(2024));
+        assert_eq!(accessor("getMonth"), Val::new_int(2));
+        assert_eq!(accessor("getDate"), Val::new_int(10));
+        assert_eq!(accessor("getDayOfMonth"), Val::new_int(9));
+        assert_eq!(accessor("getDayOfYear"), Val::new_int(69));
+        assert_eq!(accessor("getDayOfWeek"), Val::new_int(0));
+        assert_eq!(accessor("getHours"), Val::new_int(13));
+        assert_eq!(accessor("getMinutes"), Val::new_int(45));
+        assert_eq!(accessor("getSeconds"), Val::new_int(30));
+        assert_eq!(accessor("getMilliseconds"), Val::new_int(250));
+        assert_eq!(eval_program!(r#"duration('1h30m').getMinutes()"#), Val::new_int(90));
+        assert_eq!(eval_program!(r#"duration('1.5s').getMilliseconds()"#), Val::new_int(1500));
+
+        let code = |expr: &str| eval_program_str(expr).as_error().map(|e| e.code());
+        assert_eq!(code(r#"timestamp('10000-01-01T00:00:00Z')"#), Some(crate::ErrorCode::InvalidArgument));
+        assert_eq!(code(r#"timestamp('9999-12-31T23:59:59Z') + duration('1s')"#), Some(crate::ErrorCode::Overflow));
+        assert_eq!(code(r#"timestamp(-62135596801)"#), Some(crate::ErrorCode::Overflow));
+        assert_eq!(code(r#"duration('1x')"#), Some(crate::ErrorCode::InvalidArgument));
+        assert_eq!(code(r#"duration('87660000h') + duration('1h')"#), Some(crate::ErrorCode::Overflow));
+        assert_eq!(code(r#"duration('-87660001h')"#), Some(crate::ErrorCode::Overflow));
+        assert_eq!(code(r#"timestamp('0001-01-01T00:00:00Z') - timestamp('9999-12-31T23:59:59Z')"#), None);
+    }
+
+    #[test]
+    fn test_time_zones() {
+        let ts = r#"timestamp('2024-07-01T02:30:00Z')"#;
+        let accessor = |f: &str| eval_program_str(&format!("{}.{}", ts, f));
+        assert_eq!(accessor("getHours('America/New_York')"), Val::new_int(22));
+        assert_eq!(accessor("getDate('America/New_York')"), Val::new_int(30));
+        assert_eq!(accessor("getMonth('America/New_York')"), Val::new_int(5));
+        assert_eq!(accessor("getDayOfWeek('Asia/Tokyo')"), Val::new_int(1));
+        assert_eq!(accessor("getHours('+05:30')"), Val::new_int(8));
+        assert_eq!(accessor("getMinutes('-02:45')"), Val::new_int(45));
+        assert_eq!(accessor("getHours('UTC')"), Val::new_int(2));
+        assert_eq!(
+            accessor("getHours('Mars/Olympus')").as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_strings_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Strings);
+
+        assert_eq!(eval_program!(r#"'héllo'.charAt(1)"#, &mut ctx), Val::new_string("é"));
+        assert_eq!(eval_program!(r#"'héllo'.charAt(5)"#, &mut ctx), Val::new_string(""));
+        assert_eq!(eval_program!(r#"'héllo héllo'.indexOf('llo')"#, &mut ctx), Val::new_int(2));
+        assert_eq!(eval_program!(r#"'héllo héllo'.indexOf('llo', 3)"#, &mut ctx), Val::new_int(8));
+        assert_eq!(eval_program!(r#"'héllo héllo'.lastIndexOf('é')"#, &mut ctx), Val::new_int(7));
+        assert_eq!(eval_program!(r#"'héllo héllo'.lastIndexOf('é', 6)"#, &mut ctx), Val::new_int(1));
+        assert_eq!(eval_program!(r#"'Héllo'.lowerAscii()"#, &mut ctx), Val::new_string("héllo"));
+        assert_eq!(eval_program!(r#"'héllo'.upperAscii()"#, &mut ctx), Val::new_string("HéLLO"));
+        assert_eq!(eval_program!(r#"'a.b.c'.replace('.', '/', 1)"#, &mut ctx), Val::new_string("a/b.c"));
+        assert_eq!(
+            eval_program!(r#"'a,b,c'.split(',', 2)"#, &mut ctx),
+            Val::new_list(vec![Val::new_string("a"), Val::new_string("b,c")].into())
+        );
+        assert_eq!(eval_program!(r#"'héllo'.substring(1, 3)"#, &mut ctx), Val::new_string("él"));
+        assert_eq!(eval_program!(r#"'  hi \n'.trim()"#, &mut ctx), Val::new_string("hi"));
+        assert_eq!(eval_program!(r#"['a', 'b'].join('-')"#, &mut ctx), Val::new_string("a-b"));
+        assert_eq!(eval_program!(r#"'héllo'.reverse()"#, &mut ctx), Val::new_string("olléh"));
+        assert_eq!(eval_program!(r#"strings.quote('say "hi"\n')"#, &mut ctx), Val::new_string(r#""say \"hi\"\n""#));
+
+        assert_eq!(
+            eval_program!(r#"'abc'.charAt(4)"#, &mut ctx),
+            Val::new_error("index out of range: 4".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"'abc'.substring(2, 1)"#, &mut ctx),
+            Val::new_error("invalid substring range. start: 2, end: 1".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"'abc'.indexOf('b', -1)"#, &mut ctx),
+            Val::new_error("index out of range: -1".to_string())
+        );
+        assert!(crate::Program::new(r#"strings.quote('a').size() == 3"#).unwrap().check(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_namespaced_function_shadowed_by_variable() {
+        use crate::function::{Function, Overload};
+        use crate::value::ty::Ty;
+
+        fn zero(_: Vec<Val>) -> Val {
+            Val::new_int(0)
+        }
+        const ZERO: &[Overload] = &[Overload { key: "zero_size", args: &[], result: Ty::Int, func: zero, strict: true }];
+
+        let mut ctx = crate::Context::default();
+        ctx.add_function("name.size", Function { name: "name.size", overloads: ZERO });
+        ctx.add_function("a.b.size", Function { name: "a.b.size", overloads: ZERO });
+        assert_eq!(eval_program!(r#"name.size()"#, &mut ctx), Val::new_int(0));
+        assert_eq!(eval_program!(r#"a.b.size()"#, &mut ctx), Val::new_int(0));
+
+        ctx.add_variable("name", Val::new_string("abc"));
+        ctx.add_variable("a.b", Val::new_string("abcd"));
+        assert_eq!(eval_program!(r#"name.size()"#, &mut ctx), Val::new_int(3));
+        assert_eq!(eval_program!(r#"a.b.size()"#, &mut ctx), Val::new_int(4));
+        assert!(crate::Program::new(r#"name.size() == 3"#).unwrap().check(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_format() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Strings);
+
+        assert_eq!(
+            eval_program!(r#"'%s is %d years'.format(['Ada', 36])"#, &mut ctx),
+            Val::new_string("Ada is 36 years")
+        );
+        assert_eq!(eval_program!(r#"'%.2f%%'.format([99.456])"#, &mut ctx), Val::new_string("99.46%"));
+        assert_eq!(eval_program!(r#"'%f'.format([1])"#, &mut ctx), Val::new_string("1.000000"));
+        assert_eq!(eval_program!(r#"'%e'.format([1052.032911275])"#, &mut ctx), Val::new_string("1.052033e+03"));
+        assert_eq!(eval_program!(r#"'%x %X'.format([255, 'Hi'])"#, &mut ctx), Val::new_string("ff 4869"));
+        assert_eq!(eval_program!(r#"'%o %b'.format([8, 5u])"#, &mut ctx), Val::new_string("10 101"));
+        assert_eq!(
+            eval_program!(r#"'%s'.format([[1, 'a', b'b', null, duration('1m')]])"#, &mut ctx),
+            Val::new_string(r#"[1, "a", b"b", null, duration("60s")]"#)
+        );
+        assert_eq!(
+            eval_program!(r#"'%s'.format([['tab\there', "q\"\x1b\f"]])"#, &mut ctx),
+            Val::new_string("[\"tab\\there\", \"q\\\"\x1b\\f\"]")
+        );
+        assert_eq!(
+            eval_program!(r#"'%s'.format([{'b': 2.5, 'a': true}])"#, &mut ctx),
+            Val::new_string(r#"{"a": true, "b": 2.5}"#)
+        );
+        assert_eq!(
+            eval_program!(r#"'%s'.format([timestamp('2024-01-01T00:00:00Z')])"#, &mut ctx),
+            Val::new_string("2024-01-01T00:00:00Z")
+        );
+
+        assert_eq!(
+            eval_program!(r#"'%d'.format([1.5])"#, &mut ctx),
+            Val::new_error("error during formatting: decimal clause can only be used on integers, was given double".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"'%s %s'.format(['a'])"#, &mut ctx),
+            Val::new_error("index 1 out of range".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"'%q'.format(['a'])"#, &mut ctx),
+            Val::new_error("could not parse formatting clause: unrecognized formatting clause \"q\"".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"'%.70000f'.format([1.0])"#, &mut ctx),
+            Val::new_error("could not parse formatting clause: precision exceeds 1074".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"'%.99999999999999999999e'.format([1.0])"#, &mut ctx),
+            Val::new_error("could not parse formatting clause: precision exceeds 1074".to_string())
+        );
+    }
+
+    #[test]
+    fn test_math_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Math);
+
+        assert_eq!(eval_program!(r#"math.greatest(1, 2.5, 3u)"#, &mut ctx), Val::new_uint(3));
+        assert_eq!(eval_program!(r#"math.least([-1, 2.5, 3u])"#, &mut ctx), Val::new_int(-1));
+        assert_eq!(eval_program!(r#"math.greatest(7)"#, &mut ctx), Val::new_int(7));
+        assert_eq!(eval_program!(r#"math.ceil(1.2) + math.floor(1.8)"#, &mut ctx), Val::new_double(3.0));
+        assert_eq!(eval_program!(r#"math.round(-2.5)"#, &mut ctx), Val::new_double(-3.0));
+        assert_eq!(eval_program!(r#"math.trunc(-2.7)"#, &mut ctx), Val::new_double(-2.0));
+        assert_eq!(eval_program!(r#"math.abs(-5)"#, &mut ctx), Val::new_int(5));
+        assert_eq!(eval_program!(r#"math.sign(-5.5)"#, &mut ctx), Val::new_double(-1.0));
+        assert_eq!(eval_program!(r#"math.isNaN(0.0 / 0.0)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"math.isInf(1.0 / 0.0)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"math.isFinite(1.0)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"math.sqrt(16)"#, &mut ctx), Val::new_double(4.0));
+        assert_eq!(eval_program!(r#"math.bitAnd(6, 3)"#, &mut ctx), Val::new_int(2));
+        assert_eq!(eval_program!(r#"math.bitOr(4u, 1u)"#, &mut ctx), Val::new_uint(5));
+        assert_eq!(eval_program!(r#"math.bitXor(6, 3)"#, &mut ctx), Val::new_int(5));
+        assert_eq!(eval_program!(r#"math.bitNot(0)"#, &mut ctx), Val::new_int(-1));
+        assert_eq!(eval_program!(r#"math.bitShiftLeft(1, 4)"#, &mut ctx), Val::new_int(16));
+        assert_eq!(eval_program!(r#"math.bitShiftRight(-1, 60)"#, &mut ctx), Val::new_int(15));
+        assert_eq!(eval_program!(r#"math.bitShiftLeft(1u, 64)"#, &mut ctx), Val::new_uint(0));
+
+        assert_eq!(
+            eval_program!(r#"math.abs(-9223372036854775807 - 1)"#, &mut ctx),
+            Val::new_error("integer overflow".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"math.greatest([])"#, &mut ctx),
+            Val::new_error("math.greatest(list) argument must not be empty".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"math.least(1, 'a')"#, &mut ctx),
+            Val::new_error("no matching overload for 'math.least'".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"math.bitShiftLeft(1, -1)"#, &mut ctx),
+            Val::new_error("math.bitShiftLeft() negative offset: -1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_qualified_names() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Math);
+        ctx.add_variable("a.b", Val::new_map(std::collections::HashMap::from([(Val::new_string("c"), Val::new_int(1))]).into()));
+        ctx.add_variable("a.b.c", Val::new_int(2));
+        ctx.add_variable("x", Val::new_map(std::collections::HashMap::from([(Val::new_string("y"), Val::new_int(3))]).into()));
+
+        assert_eq!(eval_program!(r#"a.b.c"#, &mut ctx), Val::new_int(2));
+        assert_eq!(eval_program!(r#"a.b['c']"#, &mut ctx), Val::new_int(1));
+        assert_eq!(eval_program!(r#".x.y"#, &mut ctx), Val::new_int(3));
+        assert_eq!(eval_program!(r#".math.greatest(1, .a.b.c)"#, &mut ctx), Val::new_int(2));
+        assert_eq!(eval_program!(r#".size('ab')"#, &mut ctx), Val::new_int(2));
+        assert_eq!(
+            eval_program!(r#"a.c"#, &mut ctx),
+            Val::new_error("unknown variable a".to_string())
+        );
+
+        let program = crate::Program::new(r#"a.b.c + .x.y"#).unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::Int);
+        assert_eq!(crate::Program::new(".x.y").unwrap().residual(&mut ctx).unwrap().to_string(), "3");
+    }
+
+    #[test]
+    fn test_container() {
+        let mut ctx = crate::Context::default();
+        ctx.container("com.acme.policy");
+        ctx.add_variable("com.acme.limit", Val::new_int(10));
+        ctx.add_variable("com.acme.policy.limit", Val::new_int(5));
+        ctx.add_variable("limit", Val::new_int(1));
+        ctx.add_variable("com.acme.v1.Config", Val::new_map(std::collections::HashMap::from([(Val::new_string("max"), Val::new_int(7))]).into()));
+        ctx.add_alias("Config", "com.acme.v1.Config");
+        ctx.add_attribute_pattern(crate::AttributePattern::new("com.acme.policy.request"));
+        ctx.declare_variable("com.acme.policy.request", crate::Type::map(crate::Type::String, crate::Type::String));
+
+        assert_eq!(eval_program!(r#"limit"#, &mut ctx), Val::new_int(5));
+        assert_eq!(eval_program!(r#"acme.limit"#, &mut ctx), Val::new_int(10));
+        assert_eq!(eval_program!(r#".limit"#, &mut ctx), Val::new_int(1));
+        assert_eq!(eval_program!(r#"Config.max"#, &mut ctx), Val::new_int(7));
+        assert_eq!(eval_program!(r#"request.path"#, &mut ctx).as_unknown().map(|_| ()), Some(()));
+        assert_eq!(crate::Program::new("limit + Config.max").unwrap().check(&ctx).unwrap(), crate::Type::Int);
+        assert!(crate::Program::new(".request").unwrap().check(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_encoders_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Encoders);
+
+        assert_eq!(eval_program!(r#"base64.encode(b'hello')"#, &mut ctx), Val::new_string("aGVsbG8="));
+        assert_eq!(eval_program!(r#"base64.decode('aGVsbG8=')"#, &mut ctx), Val::new_bytes(b"hello".to_vec().into()));
+        assert_eq!(eval_program!(r#"base64.decode('aGVsbG8')"#, &mut ctx), Val::new_bytes(b"hello".to_vec().into()));
+        assert_eq!(
+            eval_program!(r#"base64.decode('a*b')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_function_overloading() {
+        use crate::function::{Function, Overload};
+        use crate::value::ty::Ty;
+
+        fn bool_size(_: Vec<Val>) -> Val {
+            Val::new_int(1)
+        }
+        fn bytes_size(_: Vec<Val>) -> Val {
+            Val::new_int(-1)
+        }
+
+        let mut ctx = crate::Context::default();
+        ctx.add_function("size", Function {
+            name: "size",
+            overloads: &[
+                Overload { key: "size_bool", args: &[Ty::Bool], result: Ty::Int, func: bool_size, strict: true },
+                Overload { key: "size_bytes", args: &[Ty::Bytes], result: Ty::Int, func: bytes_size, strict: true },
+            ],
+        });
+
+        assert_eq!(eval_program!(r#"size(true)"#, &mut ctx), Val::new_int(1));
+        assert_eq!(eval_program!(r#"size(b'abc')"#, &mut ctx), Val::new_int(-1));
+        assert_eq!(eval_program!(r#"size('ab')"#, &mut ctx), Val::new_int(2));
+        assert_eq!(crate::Program::new("size(true) + size('ab')").unwrap().check(&ctx).unwrap(), crate::Type::Int);
+    }
+
+    #[test]
+    fn test_lists_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Lists);
+        ctx.add_library(crate::ext::Strings);
+        ctx.add_library(crate::ext::Optionals);
+        let ints = |v: &[i64]| Val::new_list(v.iter().map(|i| Val::new_int(*i)).collect::<Vec<_>>().into());
+
+        assert_eq!(eval_program!(r#"[1, 2, 3, 4].slice(1, 3)"#, &mut ctx), ints(&[2, 3]));
+        assert_eq!(
+            eval_program!(r#"[1, 2].slice(1, 3)"#, &mut ctx),
+            Val::new_error("cannot slice(1, 3), list is length 2".to_string())
+        );
+        assert_eq!(eval_program!(r#"[1, [2, [3]]].flatten()"#, &mut ctx), eval_program!(r#"[1, 2, [3]]"#));
+        assert_eq!(eval_program!(r#"[1, [2, [3]]].flatten(2)"#, &mut ctx), ints(&[1, 2, 3]));
+        assert_eq!(
+            eval_program!(r#"[1].flatten(-1)"#, &mut ctx),
+            Val::new_error("level must be non-negative".to_string())
+        );
+        assert_eq!(eval_program!(r#"lists.range(3)"#, &mut ctx), ints(&[0, 1, 2]));
+        assert_eq!(eval_program!(r#"[1, 2, 1, 3, 2].distinct()"#, &mut ctx), ints(&[1, 2, 3]));
+        assert_eq!(eval_program!(r#"[1, 2, 3].reverse()"#, &mut ctx), ints(&[3, 2, 1]));
+        assert_eq!(eval_program!(r#"'abc'.reverse()"#, &mut ctx), Val::new_string("cba"));
+        assert_eq!(eval_program!(r#"[3, 1, 2].sort()"#, &mut ctx), ints(&[1, 2, 3]));
+        assert_eq!(eval_program!(r#"['b', 'a'].sort()"#, &mut ctx), eval_program!(r#"['a', 'b']"#));
+        assert_eq!(
+            eval_program!(r#"[1, 'a'].sort()"#, &mut ctx),
+            Val::new_error("list elements must have the same type".to_string())
+        );
+        assert_eq!(eval_program!(r#"[3, -1, 2].sortBy(x, x * x)"#, &mut ctx), ints(&[-1, 2, 3]));
+        assert_eq!(
+            eval_program!(r#"[{'n': 'b'}, {'n': 'a'}].sortBy(m, m.n)[0].n"#, &mut ctx),
+            Val::new_string("a")
+        );
+        assert_eq!(eval_program!(r#"[1, 2].first().value()"#, &mut ctx), Val::new_int(1));
+        assert_eq!(eval_program!(r#"[].last().hasValue()"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"[].first().orValue(0)"#, &mut ctx), Val::new_int(0));
+        assert_eq!(eval_program!(r#"[1, 2, 1].indexOf(1)"#, &mut ctx), Val::new_int(0));
+        assert_eq!(eval_program!(r#"[1, 2, 1].lastIndexOf(1)"#, &mut ctx), Val::new_int(2));
+        assert_eq!(eval_program!(r#"[1, 2].indexOf(3)"#, &mut ctx), Val::new_int(-1));
+        assert_eq!(eval_program!(r#"'abcb'.lastIndexOf('b')"#, &mut ctx), Val::new_int(3));
+
+        let program = crate::Program::new("[3, 1].sortBy(x, x)").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::list(crate::Type::Dyn));
+        assert!(crate::Program::new("[1].sortBy(x.y, x)").is_err());
+    }
+
+    #[test]
+    fn test_heterogeneous_equality() {
+        assert_eq!(eval_program!(r#"1 == 1.0"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"1u == 1"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"1.5 != 1"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"-1 == 18446744073709551615u"#), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"[1, 2u] == [1.0, 2]"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"{1: 'a'}[1u]"#), Val::new_string("a"));
+        assert_eq!(eval_program!(r#"{1: 'a'}[1.0]"#), Val::new_string("a"));
+        assert_eq!(
+            eval_program!(r#"{1: 'a', 1u: 'b'}"#),
+            Val::new_error("Failed with repeated key".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"{1: 'a', 1: 'b'}"#),
+            Val::new_error("Failed with repeated key".to_string())
+        );
+        assert_eq!(eval_program!(r#"2.0 in [1, 2]"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"1 < 2.0"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"1 < 2u"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"2u > 1.5"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"dyn(1) < 2.0"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"-1 < 0u"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"1.0 >= 1u"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"9223372036854775807 < 9223372036854775808u"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"18446744073709551615u > 1.5"#), Val::new_bool(true));
+    }
+
+    #[test]
+    fn test_sets_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Sets);
+
+        assert_eq!(eval_program!(r#"sets.contains([1, 2, 3], [3, 1])"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"sets.contains([1, 2], [2, 4])"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"sets.contains([1, 2u, 3.0], [1.0, 2, 3u])"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"sets.contains([[1], ['a']], [[1.0]])"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"sets.contains([], [])"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"sets.equivalent([1, 1, 2], [2u, 1])"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"sets.equivalent([1, 2], [1])"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"sets.intersects(['admin', 'dev'], ['ops', 'dev'])"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"sets.intersects([1], [1.5, 2])"#, &mut ctx), Val::new_bool(false));
+    }
+
+    #[test]
+    fn test_optionals_ext() {
+        let mut ctx = crate::Context::default();
+        assert!(eval_program!(r#"optional.none()"#, &mut ctx).as_error().is_some());
+
+        ctx.add_library(crate::ext::Optionals);
+        assert_eq!(eval_program!(r#"optional.of(1).hasValue()"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"optional.none().hasValue()"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"optional.of('a').value()"#, &mut ctx), Val::new_string("a"));
+        assert_eq!(eval_program!(r#"optional.none().orValue(2)"#, &mut ctx), Val::new_int(2));
+        let err = eval_program!(r#"optional.none().value()"#, &mut ctx);
+        assert_eq!(err, Val::new_error("optional.none() dereference".to_string()));
+        assert_eq!(err.as_error().map(|e| e.code()), Some(crate::ErrorCode::InvalidArgument));
+    }
+
+    #[test]
+    fn test_regex_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Regex);
+        ctx.add_library(crate::ext::Optionals);
+
+        assert_eq!(
+            eval_program!(r#"regex.extract('user=alice', 'user=([a-z]+)')"#, &mut ctx),
+            Val::new_optional(Some(Val::new_string("alice")))
+        );
+        assert_eq!(eval_program!(r#"regex.extract('abc', '[0-9]+')"#, &mut ctx), Val::new_optional(None));
+        assert_eq!(
+            eval_program!(r#"regex.extract('a1b22', '[0-9]+').value()"#, &mut ctx),
+            Val::new_string("1")
+        );
+        assert_eq!(
+            eval_program!(r#"regex.extract('ab', '(a)(b)')"#, &mut ctx),
+            Val::new_error("regular expression has more than one capturing group: '(a)(b)'".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"regex.extractAll('id:1 id:22 x', 'id:([0-9]+)')"#, &mut ctx),
+            eval_program!(r#"['1', '22']"#)
+        );
+        assert_eq!(
+            eval_program!(r#"regex.replace('hello world', '([a-z]+) ([a-z]+)', '\\2 \\1')"#, &mut ctx),
+            Val::new_string("world hello")
+        );
+        assert_eq!(eval_program!(r#"regex.replace('aaa', 'a', 'b', 2)"#, &mut ctx), Val::new_string("bba"));
+        assert_eq!(eval_program!(r#"regex.replace('aaa', 'a', '$', -1)"#, &mut ctx), Val::new_string("$$$"));
+        assert_eq!(eval_program!(r#"regex.replace('aaa', 'a', 'b', 0)"#, &mut ctx), Val::new_string("aaa"));
+        assert_eq!(
+            eval_program!(r#"regex.replace('a', 'a', '\\1')"#, &mut ctx),
+            Val::new_error("replacement string references group 1 but regex has 0 group(s)".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"regex.extract('a', '(')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+        assert_eq!(
+            eval_program!(r#"'a'.matches('a{1000}{1000}')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+
+        let program = crate::Program::new(r#"regex.replace(s, '[0-9]', '#')"#).unwrap();
+        for (input, expected) in [("a1", "a#"), ("22", "##")] {
+            ctx.add_variable("s", Val::new_string(input));
+            assert_eq!(program.eval(&mut ctx), Val::new_string(expected));
+        }
+        assert_eq!(program.regexes.0.borrow().len(), 1);
+
+        let program = crate::Program::new(r#"lists.range(100).all(i, 'a'.matches('a{' + string(i + 1) + '}') == (i == 0))"#).unwrap();
+        ctx.add_library(crate::ext::Lists);
+        assert_eq!(program.eval(&mut ctx), Val::new_bool(true));
+        assert_eq!(program.regexes.0.borrow().len(), 64);
+
+        // The cache is released even when evaluation unwinds.
+        fn explode(_args: Vec<Val>) -> Val {
+            panic!("explode")
+        }
+        ctx.add_function("explode", crate::Function {
+            name: "explode",
+            overloads: &[crate::Overload { key: "explode", args: &[], result: crate::Ty::Dyn, func: explode, strict: true }],
+        });
+        let program = crate::Program::new(r#"'a'.matches('a') && explode()"#).unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| program.eval(&mut ctx)));
+        assert!(result.is_err());
+        assert_eq!(std::rc::Rc::strong_count(&program.regexes), 1);
+    }
+
+    #[test]
+    fn test_bind() {
+        let mut ctx = crate::Context::default();
+        ctx.add_variable("roles", Val::new_list(vec![Val::new_string("owner")].into()));
+        ctx.add_variable("x", Val::new_int(10));
+
+        assert_eq!(eval_program!(r#"cel.bind(a, 2 * 3, a + a)"#, &mut ctx), Val::new_int(12));
+        assert_eq!(
+            eval_program!(r#"cel.bind(rs, roles, ('admin' in rs) || ('owner' in rs))"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(eval_program!(r#"cel.bind(x, 1, x) + x"#, &mut ctx), Val::new_int(11));
+        assert_eq!(eval_program!(r#"cel.bind(a, 1, cel.bind(b, a + 1, [a, b]))"#, &mut ctx), eval_program!("[1, 2]"));
+        assert_eq!(eval_program!(r#"cel.bind(a, 1, cel.bind(a, a + 1, a)) + 0"#, &mut ctx), Val::new_int(2));
+        assert!(crate::Program::new("cel.bind(a.b, 1, a)").is_err());
+
+        let program = crate::Program::new("cel.bind(s, 'a' + 'b', s + s)").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::String);
+        assert_eq!(program.to_string(), "cel.bind(s, 'a' + 'b', s + s)");
+        let program = crate::Program::new("cel.bind(s, 1, s) + t").unwrap();
+        assert!(program.check(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_macros() {
+        let mut ctx = crate::Context::default();
+        let mut config = std::collections::HashMap::new();
+        config.insert(Val::new_string("port"), Val::new_int(8080));
+        config.insert(Val::new_string("workers"), Val::new_int(4));
+        ctx.add_variable("config", Val::new_map(config.into()));
+
+        assert_eq!(eval_program!(r#"has(config.port)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"has(config.host)"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"has({'a': {'b': 1}}.a.b)"#), Val::new_bool(true));
+        assert!(crate::Program::new("has(config)").is_err());
+
+        assert_eq!(eval_program!(r#"[1, 2, 3].all(x, x > 0)"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"[1, 2, 3].exists(x, x > 2)"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"[1, 2, 3].exists_one(x, x > 1)"#), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"[1, 2, 3].map(x, x * 2)"#), eval_program!("[2, 4, 6]"));
+        assert_eq!(eval_program!(r#"[1, 2, 3].map(x, x > 1, x * 2)"#), eval_program!("[4, 6]"));
+        assert_eq!(eval_program!(r#"[1, 2, 3].filter(x, x != 2)"#), eval_program!("[1, 3]"));
+        assert_eq!(eval_program!(r#"config.all(k, k.size() > 3)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"[[1], [2, 3]].map(l, l.map(x, x + 1))"#), eval_program!("[[2], [3, 4]]"));
+        // Iteration variables shadow a variable of the same name only inside the macro.
+        ctx.add_variable("x", Val::new_int(10));
+        assert_eq!(eval_program!(r#"[1, 2].map(x, x * 2)[1] + x"#, &mut ctx), Val::new_int(14));
+        assert_eq!(eval_program!(r#"[1].map(x, [2].map(x, x)[0] + x)[0] + x"#, &mut ctx), Val::new_int(13));
+        // Errors are absorbed when another element decides the result.
+        assert_eq!(eval_program!(r#"[0, 1].exists(x, 1 / x == 1)"#), Val::new_bool(true));
+        assert_eq!(
+            eval_program!(r#"[0, 1].all(x, 1 / x == 1)"#),
+            Val::new_error("division by zero".to_string())
+        );
+        assert_eq!(
+            eval_program!(r#"dyn(1).all(x, true)"#).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+
+        assert_eq!(eval_program!(r#"config.all(k, v, v > 0)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"config.exists(k, v, (k == 'port') && (v == 8080))"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"config.existsOne(k, v, v < 10)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"[5, 6].all(i, v, v == (i + 5))"#), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"['a', 'b'].transformList(i, v, v + string(i))"#), eval_program!("['a0', 'b1']"));
+        assert_eq!(eval_program!(r#"[1, 2, 3].transformList(i, v, i > 0, v * v)"#), eval_program!("[4, 9]"));
+        assert_eq!(
+            eval_program!(r#"config.transformMap(k, v, v + 1)"#, &mut ctx),
+            eval_program!("{'port': 8081, 'workers': 5}")
+        );
+        assert_eq!(
+            eval_program!(r#"config.transformMap(k, v, k == 'port', v)"#, &mut ctx),
+            eval_program!("{'port': 8080}")
+        );
+        assert_eq!(
+            eval_program!(r#"config.transformMapEntry(k, v, {string(v): k})"#, &mut ctx),
+            eval_program!("{'8080': 'port', '4': 'workers'}")
+        );
+        assert_eq!(
+            eval_program!(r#"[1, 2].transformMapEntry(i, v, {'k': v})"#),
+            Val::new_error("insert failed: key k already exists".to_string())
+        );
+        assert!(crate::Program::new("[1].all(x, x, true)").is_err());
+
+        let program = crate::Program::new("[1, 2].map(x, x * 2)").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::list(crate::Type::Int));
+        assert_eq!(program.to_string(), "[1, 2].map(x, x * 2)");
+        let program = crate::Program::new("config.all(k, v, (v > 0) && (k.size() > 0))").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::Bool);
+        let program = crate::Program::new("[1].all(x, x.size() > 0)").unwrap();
+        assert!(program.check(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_network_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Network);
+
+        assert_eq!(eval_program!(r#"isIP('10.0.0.1')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isIP('2001:db8::1')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isIP('010.0.0.1')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isIP('::ffff:10.0.0.1')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isIP('fe80::1%eth0')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"ip.isCanonical('2001:db8::1')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip.isCanonical('2001:DB8:0:0::1')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"ip('10.0.0.1') == ip('10.0.0.1')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip('127.0.0.1').isLoopback()"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip('::').isUnspecified()"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip('169.254.1.1').isLinkLocalUnicast()"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip('fe80::1').isLinkLocalUnicast()"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip('ff02::1').isLinkLocalMulticast()"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip('10.1.2.3').isGlobalUnicast()"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"ip('255.255.255.255').isGlobalUnicast()"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isCIDR('10.0.0.0/8')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isCIDR('10.0.0.0/33')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isCIDR('10.0.0.0/08')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"cidr('10.0.0.0/8').containsIP(ip('10.20.0.1'))"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"cidr('10.0.0.0/8').containsIP('11.0.0.1')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(
+            eval_program!(r#"cidr('2001:db8::/32').containsIP('2001:db8:1::1')"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(eval_program!(r#"cidr('10.0.0.0/8').containsIP('::1')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"cidr('10.0.0.0/8').containsCIDR('10.1.0.0/16')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(
+            eval_program!(r#"cidr('10.1.0.0/16').containsCIDR(cidr('10.0.0.0/8'))"#, &mut ctx),
+            Val::new_bool(false)
+        );
+        assert_eq!(
+            eval_program!(r#"cidr('10.1.2.3/8').masked() == cidr('10.0.0.0/8')"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(eval_program!(r#"ip('2001:db8::1').family()"#, &mut ctx), Val::new_int(6));
+        assert_eq!(eval_program!(r#"cidr('10.0.0.0/8').prefixLength()"#, &mut ctx), Val::new_int(8));
+        assert_eq!(eval_program!(r#"string(cidr('10.1.2.3/8').masked())"#, &mut ctx), Val::new_string("10.0.0.0/8"));
+        assert_eq!(eval_program!(r#"string(cidr('2001:db8::1/64').ip())"#, &mut ctx), Val::new_string("2001:db8::1"));
+        assert_eq!(
+            eval_program!(r#"ip('1.2.3')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+
+        let program = crate::Program::new("cidr('10.0.0.0/8').ip().family()").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::Int);
+        let program = crate::Program::new("ip('10.0.0.1').prefixLength()").unwrap();
+        assert!(program.check(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_kubernetes_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Urls);
+        ctx.add_library(crate::ext::Quantities);
+
+        assert_eq!(eval_program!(r#"isURL('https://example.com/path')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isURL('/absolute/path')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isURL('relative/path')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isURL('https://example.com:port')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(
+            eval_program!(r#"url('https://example.com') == url('https://example.com')"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(eval_program!(r#"isQuantity('500Mi')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isQuantity('1.5e3')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isQuantity('10 Gi')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isQuantity('Mi')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isQuantity('1e2147483647')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"quantity('1k') == quantity('1000')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"quantity('500m').isInteger()"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(
+            eval_program!(r#"quantity('1Gi').add(quantity('1Gi')) == quantity('2Gi')"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(eval_program!(r#"quantity('1').sub(1) == quantity('0')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(
+            eval_program!(r#"url('https://user@example.com:8080/a%20b?x=1').getScheme()"#, &mut ctx),
+            Val::new_string("https")
+        );
+        assert_eq!(
+            eval_program!(r#"url('https://user@example.com:8080/a%20b?x=1').getHost()"#, &mut ctx),
+            Val::new_string("example.com:8080")
+        );
+        assert_eq!(
+            eval_program!(r#"url('https://[::1]:80/').getHost()"#, &mut ctx),
+            Val::new_string("[::1]:80")
+        );
+        assert_eq!(
+            eval_program!(r#"url('https://[::1]:80/').getHostname()"#, &mut ctx),
+            Val::new_string("::1")
+        );
+        assert_eq!(
+            eval_program!(r#"url('https://example.com:8080/').getPort()"#, &mut ctx),
+            Val::new_string("8080")
+        );
+        assert_eq!(eval_program!(r#"url('https://example.com/').getPort()"#, &mut ctx), Val::new_string(""));
+        assert_eq!(
+            eval_program!(r#"url('https://example.com/a%20b').getEscapedPath()"#, &mut ctx),
+            Val::new_string("/a%20b")
+        );
+        assert_eq!(
+            eval_program!(r#"url('https://example.com').getEscapedPath()"#, &mut ctx),
+            Val::new_string("")
+        );
+        assert_eq!(
+            eval_program!(r#"url('https://example.com/?a=1&b=x+y&a=2').getQuery()"#, &mut ctx),
+            eval_program!(r#"{'a': ['1', '2'], 'b': ['x y']}"#, &mut ctx)
+        );
+
+        assert_eq!(
+            eval_program!(r#"quantity('500Mi').asInteger()"#, &mut ctx),
+            Val::new_int(500 * 1024 * 1024)
+        );
+        assert_eq!(eval_program!(r#"quantity('1.5Ki').asInteger()"#, &mut ctx), Val::new_int(1536));
+        assert_eq!(eval_program!(r#"quantity('250m').asApproximateFloat()"#, &mut ctx), Val::new_double(0.25));
+        assert_eq!(eval_program!(r#"quantity('1G').compareTo(quantity('1Gi'))"#, &mut ctx), Val::new_int(-1));
+        assert_eq!(
+            eval_program!(r#"quantity('-2').compareTo(quantity('-2000m'))"#, &mut ctx),
+            Val::new_int(0)
+        );
+        assert_eq!(
+            eval_program!(r#"quantity('500m').asInteger()"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument),
+        );
+        assert_eq!(
+            eval_program!(r#"quantity('1x')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument),
+        );
+        assert_eq!(
+            eval_program!(r#"quantity('1e30')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::Overflow),
+        );
+        assert_eq!(
+            eval_program!(r#"url('not a url')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument),
+        );
+
+        let program = crate::Program::new("quantity('1').add(2).asInteger()").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::Int);
+    }
+
+    #[test]
+    fn test_semver_ext() {
+        let mut ctx = crate::Context::default();
+        ctx.add_library(crate::ext::Semver);
+
+        assert_eq!(eval_program!(r#"isSemver('1.2.3')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isSemver('1.2.3-rc.1+build.5')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"isSemver('1.2')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isSemver('01.2.3')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isSemver('v1.2.3')"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"isSemver('v1.02', true)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"semver('v1.02', true) == semver('1.2.0')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"semver('1.2.3+a') == semver('1.2.3+b')"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"semver('1.9.0').isLessThan(semver('1.10.0'))"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"semver('1.10.0').isGreaterThan(semver('1.9.0'))"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"semver('1.0.0-rc.1').isLessThan(semver('1.0.0'))"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(
+            eval_program!(r#"semver('1.0.0-alpha.2').isLessThan(semver('1.0.0-alpha.10'))"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(
+            eval_program!(r#"semver('1.0.0-alpha.1').isLessThan(semver('1.0.0-alpha.beta'))"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(
+            eval_program!(r#"semver('1.0.0-alpha').isLessThan(semver('1.0.0-alpha.1'))"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(eval_program!(r#"semver('10.20.30').major()"#, &mut ctx), Val::new_int(10));
+        assert_eq!(eval_program!(r#"semver('10.20.30').minor()"#, &mut ctx), Val::new_int(20));
+        assert_eq!(eval_program!(r#"semver('10.20.30').patch()"#, &mut ctx), Val::new_int(30));
+        assert_eq!(eval_program!(r#"semver('1.2.3').compareTo(semver('1.2.4'))"#, &mut ctx), Val::new_int(-1));
+        assert_eq!(eval_program!(r#"semver('1.2.3').compareTo(semver('1.2.3'))"#, &mut ctx), Val::new_int(0));
+        assert_eq!(
+            eval_program!(r#"semver('1.2')"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+    }
+
+    /// The well-known types, plus the messages of `test_descriptor_set`.
+    fn test_descriptors() -> prost_reflect::DescriptorPool {
+        let mut pool = prost_reflect::DescriptorPool::global();
+        pool.add_file_descriptor_set(test_descriptor_set()).unwrap();
+        pool
+    }
+
+    /// `test.TestMessage` and the `test.Color` enum (proto3), and
+    /// `test.LegacyMessage` (proto2).
+    fn test_descriptor_set() -> prost_reflect::prost_types::FileDescriptorSet {
+        use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+        use prost_reflect::prost_types::{
+            DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+            FileDescriptorSet, MessageOptions,
+        };
+
+        let field = |name: &str, number: i32, label: Label, ty: Type, type_name: Option<&str>| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            json_name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(label as i32),
+            r#type: Some(ty as i32),
+            type_name: type_name.map(|t| t.to_string()),
+            ..Default::default()
+        };
+        let counts_entry = DescriptorProto {
+            name: Some("CountsEntry".to_string()),
+            field: vec![
+                field("key", 1, Label::Optional, Type::String, None),
+                field("value", 2, Label::Optional, Type::Int32, None),
+            ],
+            options: Some(MessageOptions { map_entry: Some(true), ..Default::default() }),
+            ..Default::default()
+        };
+        let test_message = DescriptorProto {
+            name: Some("TestMessage".to_string()),
+            field: vec![
+                field("single_int32", 1, Label::Optional, Type::Int32, None),
+                field("name", 2, Label::Optional, Type::String, None),
+                field("nums", 3, Label::Repeated, Type::Int64, None),
+                field("counts", 4, Label::Repeated, Type::Message, Some(".test.TestMessage.CountsEntry")),
+                field("child", 5, Label::Optional, Type::Message, Some(".test.TestMessage")),
+                field("wrapped", 6, Label::Optional, Type::Message, Some(".google.protobuf.Int32Value")),
+                field("created", 7, Label::Optional, Type::Message, Some(".google.protobuf.Timestamp")),
+                field("ratio", 8, Label::Optional, Type::Float, None),
+                field("flags", 9, Label::Optional, Type::Uint32, None),
+                field("color", 10, Label::Optional, Type::Enum, Some(".test.Color")),
+            ],
+            nested_type: vec![counts_entry],
+            ..Default::default()
+        };
+        let legacy_message = DescriptorProto {
+            name: Some("LegacyMessage".to_string()),
+            field: vec![field("count", 1, Label::Optional, Type::Int32, None)],
+            ..Default::default()
+        };
+        let color = EnumDescriptorProto {
+            name: Some("Color".to_string()),
+            value: ["RED", "GREEN"]
+                .iter()
+                .zip(0..)
+                .map(|(name, number)| EnumValueDescriptorProto {
+                    name: Some(name.to_string()),
+                    number: Some(number),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let file = vec![
+            FileDescriptorProto {
+                name: Some("test/test.proto".to_string()),
+                package: Some("test".to_string()),
+                dependency: vec!["google/protobuf/wrappers.proto".to_string(), "google/protobuf/timestamp.proto".to_string()],
+                message_type: vec![test_message],
+                enum_type: vec![color],
+                syntax: Some("proto3".to_string()),
+                ..Default::default()
+            },
+            FileDescriptorProto {
+                name: Some("test/legacy.proto".to_string()),
+                package: Some("test".to_string()),
+                message_type: vec![legacy_message],
+                syntax: Some("proto2".to_string()),
+                ..Default::default()
+            },
+        ];
+        FileDescriptorSet { file }
+    }
+
+    #[test]
+    fn test_message_values() {
+        use prost_reflect::{DynamicMessage, MapKey, ReflectMessage, Value};
+
+        let pool = test_descriptors();
+        let mut msg = DynamicMessage::new(pool.get_message_by_name("test.TestMessage").unwrap());
+        msg.set_field_by_name("single_int32", Value::I32(7));
+        msg.set_field_by_name("nums", Value::List(vec![Value::I64(1), Value::I64(2)]));
+        msg.set_field_by_name("counts", Value::Map([(MapKey::String("a".to_string()), Value::I32(1))].into()));
+        msg.set_field_by_name("ratio", Value::F32(0.5));
+        msg.set_field_by_name("flags", Value::U32(3));
+        let mut legacy = DynamicMessage::new(pool.get_message_by_name("test.LegacyMessage").unwrap());
+
+        let mut ctx = crate::Context::default();
+        ctx.add_variable("msg", Val::new_message(msg.clone()));
+        ctx.add_variable("empty", Val::new_message(DynamicMessage::new(msg.descriptor())));
+        ctx.add_variable("unset", Val::new_message(legacy.clone()));
+        legacy.set_field_by_name("count", Value::I32(0));
+        ctx.add_variable("zero", Val::new_message(legacy));
+        msg.set_field_by_name("name", Value::String("changed".to_string()));
+        ctx.add_variable("changed", Val::new_message(msg));
+
+        assert_eq!(eval_program!(r#"msg.single_int32"#, &mut ctx), Val::new_int(7));
+        assert_eq!(eval_program!(r#"msg.flags"#, &mut ctx), Val::new_uint(3));
+        assert_eq!(eval_program!(r#"msg.ratio"#, &mut ctx), Val::new_double(0.5));
+        assert_eq!(eval_program!(r#"msg.name"#, &mut ctx), Val::new_string(""));
+        assert_eq!(eval_program!(r#"msg.nums"#, &mut ctx), eval_program!(r#"[1, 2]"#, &mut ctx));
+        assert_eq!(eval_program!(r#"msg.counts"#, &mut ctx), eval_program!(r#"{'a': 1}"#, &mut ctx));
+        assert_eq!(eval_program!(r#"msg.child.single_int32"#, &mut ctx), Val::new_int(0));
+        assert_eq!(eval_program!(r#"msg.wrapped"#, &mut ctx), Val::new_null());
+        assert_eq!(eval_program!(r#"msg.created"#, &mut ctx), Val::new_timestamp(0, 0));
+        assert_eq!(
+            eval_program!(r#"msg.missing"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::NoSuchField)
+        );
+
+        assert_eq!(eval_program!(r#"has(msg.single_int32)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"has(msg.nums)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"has(msg.name)"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"has(msg.child)"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"has(empty.nums)"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"has(empty.counts)"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"has(unset.count)"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"has(zero.count)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"msg == msg"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"msg == changed"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"msg == empty"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"unset == zero"#, &mut ctx), Val::new_bool(false));
+        assert_eq!(eval_program!(r#"empty.child == empty"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"msg.nums.all(n, n > 0)"#, &mut ctx), Val::new_bool(true));
+    }
+
+    #[test]
+    fn test_message_construction() {
+        let mut ctx = crate::Context::default();
+        ctx.set_type_provider(crate::ProtoTypeProvider::new(test_descriptors()));
+
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{single_int32: 1}.single_int32 == 1"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{nums: [1, 2], counts: {'a': 1}}.counts.a == 1"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{child: test.TestMessage{name: 'x'}}.child.name == 'x'"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(eval_program!(r#"test.TestMessage{wrapped: 5}.wrapped == 5"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"test.TestMessage{wrapped: null}.wrapped == null"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"has(test.TestMessage{wrapped: 0}.wrapped)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{created: timestamp('2024-01-02T03:04:05Z')}.created == timestamp('2024-01-02T03:04:05Z')"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{ratio: 0.5, flags: 2u}.flags == 2u"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{single_int32: 0} == test.TestMessage{}"#, &mut ctx),
+            Val::new_bool(true)
+        );
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{single_int32: 1} == test.TestMessage{}"#, &mut ctx),
+            Val::new_bool(false)
+        );
+        assert_eq!(eval_program!(r#"has(test.LegacyMessage{count: 0}.count)"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#".test.LegacyMessage{}.count == 0"#, &mut ctx), Val::new_bool(true));
+        assert_eq!(eval_program!(r#"google.protobuf.Int64Value{value: 3} == 3"#, &mut ctx), Val::new_bool(true));
+
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{missing: 1}"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::NoSuchField)
+        );
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{single_int32: 'a'}"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::InvalidArgument)
+        );
+        assert_eq!(
+            eval_program!(r#"test.TestMessage{single_int32: 2147483648}"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::Overflow)
+        );
+        assert_eq!(
+            eval_program!(r#"test.Missing{}"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::UnknownType)
+        );
+
+        ctx.container("test");
+        assert_eq!(eval_program!(r#"TestMessage{name: 'short'}.name"#, &mut ctx), Val::new_string("short"));
+
+        let program = crate::Program::new("TestMessage{child: TestMessage{}}.child.nums").unwrap();
+        assert_eq!(program.check(&ctx).unwrap(), crate::Type::list(crate::Type::Int));
+        for expr in ["TestMessage{name: 1}", "TestMessage{missing: 1}", "TestMessage{}.missing", "Missing{}"] {
+            assert!(crate::Program::new(expr).unwrap().check(&ctx).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_file_descriptor_set() {
+        use prost_reflect::prost::Message;
+
+        let bytes = test_descriptor_set().encode_to_vec();
+        let mut ctx = crate::Context::default();
+        ctx.add_file_descriptor_set(&bytes).unwrap().container("test");
+
+        assert_eq!(eval_program!(r#"TestMessage{color: Color.GREEN}.color"#, &mut ctx), Val::new_int(1));
+        assert_eq!(eval_program!(r#"test.Color.RED"#, &mut ctx), Val::new_int(0));
+        assert_eq!(eval_program!(r#"google.protobuf.Duration{seconds: 90}"#, &mut ctx), Val::new_duration(90, 0));
+
+        let path = std::env::temp_dir().join(format!("cel-rs-test-{}.binpb", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        let mut from_file = crate::Context::default();
+        from_file.add_file_descriptor_set_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(eval_program!(r#"test.LegacyMessage{count: 2}.count"#, &mut from_file), Val::new_int(2));
+        assert!(crate::Context::default().add_file_descriptor_set(b"not a descriptor set").is_err());
+        assert!(crate::Context::default().add_file_descriptor_set_file(&path).is_err());
+
+        let msg = eval_program!(r#"test.TestMessage{single_int32: 3}"#, &mut from_file);
+        from_file.add_variable("msg", msg);
+        let program = crate::Program::new("msg.single_int32 + msg.color").unwrap();
+        assert_eq!(program.check(&from_file).unwrap(), crate::Type::Int);
+        assert!(crate::Program::new("msg.missing").unwrap().check(&from_file).is_err());
+        assert_eq!(program.eval(&mut from_file), Val::new_int(3));
+
+        // Variables keep their values next to enum constants of the same name.
+        let mut shadowed = crate::Context::default();
+        shadowed.add_variable("test.Color.RED", Val::new_int(7));
+        shadowed.add_file_descriptor_set(&bytes).unwrap();
+        shadowed.add_file_descriptor_set(&bytes).unwrap();
+        assert_eq!(eval_program!(r#"test.Color.RED"#, &mut shadowed), Val::new_int(7));
+        assert_eq!(eval_program!(r#"test.Color.GREEN"#, &mut shadowed), Val::new_int(1));
+    }
+
+    #[test]
+    fn test_file_descriptor_set_environment() {
+        use prost_reflect::prost::Message;
+        use prost_reflect::prost_types::{DescriptorProto, FileDescriptorProto};
+
+        struct Things;
+        impl crate::TypeProvider for Things {
+            fn has_type(&self, name: &str) -> bool {
+                name == "Thing"
+            }
+            fn field_type(&self, _type_name: &str, _field: &str) -> Option<crate::Type> {
+                None
+            }
+            fn new_value(&self, _type_name: &str, _fields: Vec<(&str, Val)>) -> Val {
+                Val::new_string("thing")
+            }
+        }
+
+        let mut ctx = crate::Context::default();
+        ctx.set_type_provider(Things);
+        ctx.add_file_descriptor_set(&test_descriptor_set().encode_to_vec()).unwrap();
+        assert_eq!(eval_program!(r#"Thing{}"#, &mut ctx), Val::new_string("thing"));
+        assert_eq!(eval_program!(r#"test.TestMessage{single_int32: 1}.single_int32"#, &mut ctx), Val::new_int(1));
+
+        // Descriptors registered globally by other code stay out of the environment.
+        prost_reflect::DescriptorPool::add_global_file_descriptor_proto::<&[u8]>(FileDescriptorProto {
+            name: Some("global/global.proto".to_string()),
+            package: Some("global".to_string()),
+            message_type: vec![DescriptorProto { name: Some("Global".to_string()), ..Default::default() }],
+            ..Default::default()
+        })
+        .unwrap();
+        let mut ctx = crate::Context::default();
+        ctx.add_file_descriptor_set(&test_descriptor_set().encode_to_vec()).unwrap();
+        assert_eq!(
+            eval_program!(r#"global.Global{}"#, &mut ctx).as_error().map(|e| e.code()),
+            Some(crate::ErrorCode::UnknownType)
+        );
+        assert_eq!(eval_program!(r#"google.protobuf.Int64Value{value: 3}"#, &mut ctx), Val::new_int(3));
+    }
+
+    #[test]
+    fn test_errors() {
+        use crate::function::{Function, Overload};
+        use crate::value::error::{Error, ErrorCode};
+        use crate::value::ty::Ty;
+
+        fn parse(args: Vec<Val>) -> Val {
+            match args[0].as_string().unwrap().parse::<i64>() {
+                Ok(i) => Val::new_int(i),
+                Err(e) => Error::with_source(ErrorCode::InvalidArgument, e),
+            }
+        }
+
+        let mut ctx = crate::Context::default();
+        ctx.add_variable("m", Val::new_map(std::collections::HashMap::new().into()));
+        ctx.add_function("parse", Function {
+            name: "parse",
+            overloads: &[Overload { key: "parse_string", args: &[Ty::String], result: Ty::Int, func: parse, strict: true }],
+        });
+
+        let code = |expr: &str, ctx: &mut crate::Context| {
+            crate::Program::new(expr).unwrap().eval(ctx).as_error().map(|e| e.code())
+        };
+        assert_eq!(code("m.a", &mut ctx), Some(ErrorCode::NoSuchKey));
+        assert_eq!(code("1 + 'a'", &mut ctx), Some(ErrorCode::NoMatchingOverload));
+        assert_eq!(code("1 / 0", &mut ctx), Some(ErrorCode::DivisionByZero));
+        assert_eq!(code("9223372036854775807 + 1", &mut ctx), Some(ErrorCode::Overflow));
+        assert_eq!(code("[1][3]", &mut ctx), Some(ErrorCode::InvalidArgument));
+        assert_eq!(code("missing", &mut ctx), Some(ErrorCode::UnknownVariable));
+        assert_eq!(code("1 + 1", &mut ctx), None);
+
+        let err = crate::Program::new("true &&\n  (2 / 0 > 1)").unwrap().try_eval(&mut ctx).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::DivisionByZero);
+        assert_eq!(err.error().id(), Some(4));
+        assert_eq!(err.to_string(), "<input>:2:4: division by zero");
+
+        let err = crate::Program::new("parse('x')").unwrap().try_eval(&mut ctx).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidArgument);
+        assert!(std::error::Error::source(&err).unwrap().is::<std::num::ParseIntError>());
+        assert_eq!(crate::Program::new("parse('12')").unwrap().try_eval(&mut ctx).unwrap(), Val::new_int(12));
+    }
+
 
 //     fn calc_string_string(args: Vec<Value>) -> Value {
 //         println!("{:?}", args);
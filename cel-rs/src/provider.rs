@@ -0,0 +1,132 @@
+use std::rc::Rc;
+
+use prost_reflect::{DescriptorPool, DynamicMessage, FieldDescriptor, Kind};
+
+use crate::checker::Type;
+use crate::value::error::Error;
+use crate::value::message::to_field_value;
+use crate::{Ty, Val, Value};
+
+/// Knows the message types that construction expressions such as
+/// `pkg.Msg{field: value}` can build, and the types of their fields.
+pub trait TypeProvider {
+    /// Whether `name`, fully qualified, is a type this provider can build.
+    fn has_type(&self, name: &str) -> bool;
+
+    /// The type of a field of the named type, or `None` if it has no such field.
+    fn field_type(&self, type_name: &str, field: &str) -> Option<Type>;
+
+    /// Builds a value of the named type from its field values, returning an error
+    /// value for unknown fields or values of the wrong type.
+    fn new_value(&self, type_name: &str, fields: Vec<(&str, Val)>) -> Val;
+}
+
+/// Several providers together, each type built by the first that has it.
+pub(crate) struct TypeProviders(pub(crate) Vec<Rc<dyn TypeProvider>>);
+
+impl TypeProviders {
+    fn provider(&self, name: &str) -> Option<&dyn TypeProvider> {
+        self.0.iter().find(|p| p.has_type(name)).map(|p| p.as_ref())
+    }
+}
+
+impl TypeProvider for TypeProviders {
+    fn has_type(&self, name: &str) -> bool {
+        self.provider(name).is_some()
+    }
+
+    fn field_type(&self, type_name: &str, field: &str) -> Option<Type> {
+        self.provider(type_name)?.field_type(type_name, field)
+    }
+
+    fn new_value(&self, type_name: &str, fields: Vec<(&str, Val)>) -> Val {
+        match self.provider(type_name) {
+            Some(provider) => provider.new_value(type_name, fields),
+            None => Error::unknown_type(type_name),
+        }
+    }
+}
+
+/// A [`TypeProvider`] for the protobuf messages of a descriptor pool.
+pub struct ProtoTypeProvider {
+    pool: DescriptorPool,
+}
+
+impl ProtoTypeProvider {
+    pub fn new(pool: DescriptorPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &DescriptorPool {
+        &self.pool
+    }
+}
+
+impl TypeProvider for ProtoTypeProvider {
+    fn has_type(&self, name: &str) -> bool {
+        self.pool.get_message_by_name(name).is_some()
+    }
+
+    fn field_type(&self, type_name: &str, field: &str) -> Option<Type> {
+        let field = self.pool.get_message_by_name(type_name)?.get_field_by_name(field)?;
+        Some(field_type(&field))
+    }
+
+    fn new_value(&self, type_name: &str, fields: Vec<(&str, Val)>) -> Val {
+        let Some(desc) = self.pool.get_message_by_name(type_name) else {
+            return Error::unknown_type(type_name);
+        };
+        let mut msg = DynamicMessage::new(desc.clone());
+        for (name, val) in fields {
+            let Some(field) = desc.get_field_by_name(name) else {
+                return Error::no_such_field(name);
+            };
+            // Setting a message field to null leaves it unset.
+            if val.ty() == Ty::Null && matches!(field.kind(), Kind::Message(_)) && !field.is_list() {
+                continue;
+            }
+            match to_field_value(&val, &field) {
+                Ok(v) => msg.set_field(&field, v),
+                Err(e) => return e,
+            }
+        }
+        Val::new_message(msg)
+    }
+}
+
+fn field_type(field: &FieldDescriptor) -> Type {
+    if field.is_map() {
+        let Kind::Message(entry) = field.kind() else { unreachable!("map fields hold entries") };
+        return Type::map(kind_type(&entry.map_entry_key_field().kind()), kind_type(&entry.map_entry_value_field().kind()));
+    }
+    let elem = kind_type(&field.kind());
+    if field.is_list() {
+        return Type::list(elem);
+    }
+    elem
+}
+
+/// The CEL type of a protobuf kind. Wrapper types are `dyn`, since they may also
+/// be `null`.
+fn kind_type(kind: &Kind) -> Type {
+    match kind {
+        Kind::Double | Kind::Float => Type::Double,
+        Kind::Int32 | Kind::Int64 | Kind::Sint32 | Kind::Sint64 | Kind::Sfixed32 | Kind::Sfixed64 | Kind::Enum(_) => {
+            Type::Int
+        }
+        Kind::Uint32 | Kind::Uint64 | Kind::Fixed32 | Kind::Fixed64 => Type::UInt,
+        Kind::Bool => Type::Bool,
+        Kind::String => Type::String,
+        Kind::Bytes => Type::Bytes,
+        Kind::Message(desc) => match desc.full_name() {
+            "google.protobuf.Timestamp" => Type::Timestamp,
+            "google.protobuf.Duration" => Type::Duration,
+            "google.protobuf.Struct" => Type::map(Type::String, Type::Dyn),
+            "google.protobuf.ListValue" => Type::list(Type::Dyn),
+            name if name.starts_with("google.protobuf.") && (name.ends_with("Value") || name == "google.protobuf.Any") => {
+                Type::Dyn
+            }
+            name => Type::Message(name.to_string()),
+        },
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{function::{Function, Overload}, Val};
+use std::rc::Rc;
+
+use crate::{
+    function::{Function, Overload},
+    value::{duration::format_duration, error::Error, map::describe_key, timestamp::format_timestamp, ty::Ty},
+    Val,
+};
 
 
 fn invoke_dyn(args: Vec<Val>) -> Val {
@@ -10,9 +16,252 @@ pub fn new_dyn() -> Function {
         name: "dyn",
         overloads: &[Overload {
             key: "dyn",
+            args: &[Ty::Dyn],
+            result: Ty::Dyn,
             func: invoke_dyn,
+            strict: true,
+        }],
+    }
+}
+
+fn invoke_not_strictly_false(args: Vec<Val>) -> Val {
+    match args.first().unwrap().as_bool() {
+        Some(b) => Val::new_bool(*b),
+        None => Val::new_bool(true),
+    }
+}
+
+/// `@not_strictly_false` treats errors and unknowns as `true`, letting comprehension
+/// loop conditions keep iterating past them.
+pub fn new_not_strictly_false() -> Function {
+    Function {
+        name: "@not_strictly_false",
+        overloads: &[Overload {
+            key: "not_strictly_false",
+            args: &[Ty::Dyn],
+            result: Ty::Bool,
+            func: invoke_not_strictly_false,
+            strict: false,
+        }],
+    }
+}
+
+fn invoke_map_insert(args: Vec<Val>) -> Val {
+    let mut map = args[0].as_map().unwrap().clone();
+    let entries: Vec<(Val, Val)> = match args.len() {
+        3 => vec![(args[1].clone(), args[2].clone())],
+        _ => args[1].as_map().unwrap().iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+    };
+    for (key, value) in entries {
+        if map.contains_key(&key) {
+            return Error::invalid_argument(format!("insert failed: key {} already exists", describe_key(&key)));
+        }
+        map.insert(key, value);
+    }
+    Val::new_map(Rc::new(map))
+}
+
+/// `@mapInsert` adds a key and value, or the entries of another map, to a map.
+/// `transformMap` and `transformMapEntry` expand to it.
+pub fn new_map_insert() -> Function {
+    Function {
+        name: "@mapInsert",
+        overloads: &[
+            Overload { key: "map_insert_key_value", args: &[Ty::Map, Ty::Dyn, Ty::Dyn], result: Ty::Map, func: invoke_map_insert, strict: true },
+            Overload { key: "map_insert_map", args: &[Ty::Map, Ty::Map], result: Ty::Map, func: invoke_map_insert, strict: true },
+        ],
+    }
+}
+
+fn invoke_size(args: Vec<Val>) -> Val {
+    let arg = args.first().unwrap();
+    let size = if let Some(s) = arg.as_string() {
+        s.chars().count()
+    } else if let Some(b) = arg.as_bytes() {
+        b.len()
+    } else if let Some(l) = arg.as_list() {
+        l.len()
+    } else if let Some(m) = arg.as_map() {
+        m.len()
+    } else {
+        return Error::no_matching_overload("size");
+    };
+    Val::new_int(size as i64)
+}
+
+/// `size` counts the code points of a string, the bytes of bytes and the entries of
+/// a list or map.
+pub fn new_size() -> Function {
+    Function {
+        name: "size",
+        overloads: &[
+            Overload { key: "size_string", args: &[Ty::String], result: Ty::Int, func: invoke_size, strict: true },
+            Overload { key: "size_bytes", args: &[Ty::Bytes], result: Ty::Int, func: invoke_size, strict: true },
+            Overload { key: "size_list", args: &[Ty::List], result: Ty::Int, func: invoke_size, strict: true },
+            Overload { key: "size_map", args: &[Ty::Map], result: Ty::Int, func: invoke_size, strict: true },
+        ],
+    }
+}
+
+fn strings(args: &[Val]) -> (&str, &str) {
+    (args[0].as_string().unwrap(), args[1].as_string().unwrap())
+}
+
+fn invoke_contains(args: Vec<Val>) -> Val {
+    let (s, sub) = strings(&args);
+    Val::new_bool(s.contains(sub))
+}
+
+pub fn new_contains() -> Function {
+    Function {
+        name: "contains",
+        overloads: &[Overload {
+            key: "contains_string",
+            args: &[Ty::String, Ty::String],
+            result: Ty::Bool,
+            func: invoke_contains,
+            strict: true,
         }],
     }
 }
 
+fn invoke_starts_with(args: Vec<Val>) -> Val {
+    let (s, prefix) = strings(&args);
+    Val::new_bool(s.starts_with(prefix))
+}
+
+pub fn new_starts_with() -> Function {
+    Function {
+        name: "startsWith",
+        overloads: &[Overload {
+            key: "starts_with_string",
+            args: &[Ty::String, Ty::String],
+            result: Ty::Bool,
+            func: invoke_starts_with,
+            strict: true,
+        }],
+    }
+}
 
+fn invoke_ends_with(args: Vec<Val>) -> Val {
+    let (s, suffix) = strings(&args);
+    Val::new_bool(s.ends_with(suffix))
+}
+
+pub fn new_ends_with() -> Function {
+    Function {
+        name: "endsWith",
+        overloads: &[Overload {
+            key: "ends_with_string",
+            args: &[Ty::String, Ty::String],
+            result: Ty::Bool,
+            func: invoke_ends_with,
+            strict: true,
+        }],
+    }
+}
+
+fn invoke_matches(args: Vec<Val>) -> Val {
+    let (s, pattern) = strings(&args);
+    match crate::pattern::compile(pattern) {
+        Ok(re) => Val::new_bool(re.is_match(s)),
+        Err(e) => e,
+    }
+}
+
+/// `matches` reports whether the string contains a match of an RE2 pattern. The
+/// pattern is not anchored.
+pub fn new_matches() -> Function {
+    Function {
+        name: "matches",
+        overloads: &[Overload {
+            key: "matches_string",
+            args: &[Ty::String, Ty::String],
+            result: Ty::Bool,
+            func: invoke_matches,
+            strict: true,
+        }],
+    }
+}
+
+fn to_string(args: Vec<Val>) -> Val {
+    let arg = &args[0];
+    if let Some(t) = arg.as_timestamp() {
+        return Val::new_string(format_timestamp(t));
+    }
+    if let Some(d) = arg.as_duration() {
+        return Val::new_string(format_duration(d));
+    }
+    if let Some(i) = arg.as_int() {
+        return Val::new_string(i);
+    }
+    if let Some(u) = arg.as_uint() {
+        return Val::new_string(u);
+    }
+    if let Some(d) = arg.as_double() {
+        return Val::new_string(d);
+    }
+    if let Some(b) = arg.as_bool() {
+        return Val::new_string(b);
+    }
+    if let Some(b) = arg.as_bytes() {
+        return match std::str::from_utf8(b) {
+            Ok(s) => Val::new_string(s),
+            Err(_) => Error::invalid_argument("invalid UTF-8 in bytes, cannot convert to string".to_string()),
+        };
+    }
+    arg.clone()
+}
+
+pub fn new_string() -> Function {
+    Function {
+        name: "string",
+        overloads: &[
+            Overload { key: "string_to_string", args: &[Ty::String], result: Ty::String, func: to_string, strict: true },
+            Overload { key: "int64_to_string", args: &[Ty::Int], result: Ty::String, func: to_string, strict: true },
+            Overload { key: "uint64_to_string", args: &[Ty::UInt], result: Ty::String, func: to_string, strict: true },
+            Overload { key: "double_to_string", args: &[Ty::Double], result: Ty::String, func: to_string, strict: true },
+            Overload { key: "bool_to_string", args: &[Ty::Bool], result: Ty::String, func: to_string, strict: true },
+            Overload { key: "bytes_to_string", args: &[Ty::Bytes], result: Ty::String, func: to_string, strict: true },
+            Overload { key: "timestamp_to_string", args: &[Ty::Timestamp], result: Ty::String, func: to_string, strict: true },
+            Overload { key: "duration_to_string", args: &[Ty::Duration], result: Ty::String, func: to_string, strict: true },
+        ],
+    }
+}
+
+fn to_int(args: Vec<Val>) -> Val {
+    let arg = &args[0];
+    if let Some(t) = arg.as_timestamp() {
+        return Val::new_int(t.timestamp());
+    }
+    if let Some(u) = arg.as_uint() {
+        return i64::try_from(*u).map_or_else(|_| Error::overflow("integer"), Val::new_int);
+    }
+    if let Some(d) = arg.as_double() {
+        // Doubles at the bounds are rejected too, since they may have been rounded.
+        if !d.is_finite() || *d <= i64::MIN as f64 || *d >= i64::MAX as f64 {
+            return Error::overflow("integer");
+        }
+        return Val::new_int(d.trunc() as i64);
+    }
+    if let Some(s) = arg.as_string() {
+        return match s.parse() {
+            Ok(i) => Val::new_int(i),
+            Err(e) => Error::invalid_argument(format!("cannot convert '{}' to int: {}", s, e)),
+        };
+    }
+    arg.clone()
+}
+
+pub fn new_int() -> Function {
+    Function {
+        name: "int",
+        overloads: &[
+            Overload { key: "int64_to_int64", args: &[Ty::Int], result: Ty::Int, func: to_int, strict: true },
+            Overload { key: "uint64_to_int64", args: &[Ty::UInt], result: Ty::Int, func: to_int, strict: true },
+            Overload { key: "double_to_int64", args: &[Ty::Double], result: Ty::Int, func: to_int, strict: true },
+            Overload { key: "string_to_int64", args: &[Ty::String], result: Ty::Int, func: to_int, strict: true },
+            Overload { key: "timestamp_to_int64", args: &[Ty::Timestamp], result: Ty::Int, func: to_int, strict: true },
+        ],
+    }
+}
@@ -0,0 +1,279 @@
+//! Timestamp and duration constructors and accessors.
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, TimeDelta, Timelike, Utc};
+
+use crate::{
+    function::{Function, Overload},
+    value::{
+        duration::{checked_duration, parse_duration},
+        error::Error,
+        timestamp::checked_timestamp,
+        ty::Ty,
+    },
+    Val,
+};
+
+fn timestamp_from_string(args: Vec<Val>) -> Val {
+    let s = args[0].as_string().unwrap();
+    match DateTime::parse_from_rfc3339(s) {
+        Ok(t) => checked_timestamp(t.with_timezone(&Utc)),
+        Err(e) => Error::invalid_argument(format!("invalid timestamp '{}': {}", s, e)),
+    }
+}
+
+fn timestamp_from_int(args: Vec<Val>) -> Val {
+    Val::new_timestamp(*args[0].as_int().unwrap(), 0)
+}
+
+fn identity(args: Vec<Val>) -> Val {
+    args[0].clone()
+}
+
+/// `timestamp` parses an RFC 3339 string or takes seconds since the Unix epoch.
+pub fn new_timestamp() -> Function {
+    Function {
+        name: "timestamp",
+        overloads: &[
+            Overload { key: "string_to_timestamp", args: &[Ty::String], result: Ty::Timestamp, func: timestamp_from_string, strict: true },
+            Overload { key: "int_to_timestamp", args: &[Ty::Int], result: Ty::Timestamp, func: timestamp_from_int, strict: true },
+            Overload { key: "timestamp_to_timestamp", args: &[Ty::Timestamp], result: Ty::Timestamp, func: identity, strict: true },
+        ],
+    }
+}
+
+fn duration_from_string(args: Vec<Val>) -> Val {
+    let s = args[0].as_string().unwrap();
+    match parse_duration(s) {
+        Some(d) => checked_duration(d),
+        None => Error::invalid_argument(format!("invalid duration '{}'", s)),
+    }
+}
+
+/// `duration` parses strings such as `1h30m` or `-0.5s`.
+pub fn new_duration() -> Function {
+    Function {
+        name: "duration",
+        overloads: &[
+            Overload { key: "string_to_duration", args: &[Ty::String], result: Ty::Duration, func: duration_from_string, strict: true },
+            Overload { key: "duration_to_duration", args: &[Ty::Duration], result: Ty::Duration, func: identity, strict: true },
+        ],
+    }
+}
+
+/// The timestamp as wall-clock time, in the time zone given as the second argument
+/// or else in UTC.
+fn timestamp(args: &[Val]) -> Result<NaiveDateTime, Val> {
+    let t = args[0].as_timestamp().unwrap();
+    match args.get(1) {
+        Some(tz) => in_zone(t, tz.as_string().unwrap()),
+        None => Ok(t.naive_utc()),
+    }
+}
+
+/// Converts to a zone given either by IANA name, such as `America/New_York`, or
+/// as a fixed offset such as `+05:30`.
+fn in_zone(t: &DateTime<Utc>, tz: &str) -> Result<NaiveDateTime, Val> {
+    if let Some(offset) = parse_offset(tz) {
+        return Ok(t.with_timezone(&offset).naive_local());
+    }
+    match tz.parse::<chrono_tz::Tz>() {
+        Ok(zone) => Ok(t.with_timezone(&zone).naive_local()),
+        Err(_) => Err(Error::invalid_argument(format!("unknown time zone '{}'", tz))),
+    }
+}
+
+/// Parses `[+-]HH:MM`, where a missing sign means a positive offset.
+fn parse_offset(tz: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match tz.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, tz.strip_prefix('+').unwrap_or(tz)),
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Applies an accessor to the timestamp argument, passing on time zone errors.
+fn with_timestamp(args: &[Val], accessor: fn(&NaiveDateTime) -> u32) -> Val {
+    match timestamp(args) {
+        Ok(t) => Val::new_int(accessor(&t) as i64),
+        Err(e) => e,
+    }
+}
+
+fn duration(args: &[Val]) -> TimeDelta {
+    *args[0].as_duration().unwrap()
+}
+
+fn timestamp_full_year(args: Vec<Val>) -> Val {
+    // Years are always positive, as timestamps start at year 1.
+    with_timestamp(&args, |t| t.year() as u32)
+}
+
+fn timestamp_month(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.month0())
+}
+
+fn timestamp_day_of_year(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.ordinal0())
+}
+
+fn timestamp_day_of_month(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.day0())
+}
+
+fn timestamp_date(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.day())
+}
+
+fn timestamp_day_of_week(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.weekday().num_days_from_sunday())
+}
+
+fn timestamp_hours(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.hour())
+}
+
+fn timestamp_minutes(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.minute())
+}
+
+fn timestamp_seconds(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.second())
+}
+
+fn timestamp_milliseconds(args: Vec<Val>) -> Val {
+    with_timestamp(&args, |t| t.nanosecond() / 1_000_000)
+}
+
+fn duration_hours(args: Vec<Val>) -> Val {
+    Val::new_int(duration(&args).num_hours())
+}
+
+fn duration_minutes(args: Vec<Val>) -> Val {
+    Val::new_int(duration(&args).num_minutes())
+}
+
+fn duration_seconds(args: Vec<Val>) -> Val {
+    Val::new_int(duration(&args).num_seconds())
+}
+
+fn duration_milliseconds(args: Vec<Val>) -> Val {
+    Val::new_int(duration(&args).num_milliseconds())
+}
+
+// Months and days of the year, month and week count from zero; `getDate` is the
+// one-based day of the month. Timestamp accessors take an optional time zone and
+// default to UTC.
+
+pub fn new_get_full_year() -> Function {
+    Function {
+        name: "getFullYear",
+        overloads: &[
+            Overload { key: "timestamp_to_year", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_full_year, strict: true },
+            Overload { key: "timestamp_to_year_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_full_year, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_month() -> Function {
+    Function {
+        name: "getMonth",
+        overloads: &[
+            Overload { key: "timestamp_to_month", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_month, strict: true },
+            Overload { key: "timestamp_to_month_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_month, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_day_of_year() -> Function {
+    Function {
+        name: "getDayOfYear",
+        overloads: &[
+            Overload { key: "timestamp_to_day_of_year", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_day_of_year, strict: true },
+            Overload { key: "timestamp_to_day_of_year_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_day_of_year, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_day_of_month() -> Function {
+    Function {
+        name: "getDayOfMonth",
+        overloads: &[
+            Overload { key: "timestamp_to_day_of_month", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_day_of_month, strict: true },
+            Overload { key: "timestamp_to_day_of_month_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_day_of_month, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_date() -> Function {
+    Function {
+        name: "getDate",
+        overloads: &[
+            Overload { key: "timestamp_to_day_of_month_1_based", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_date, strict: true },
+            Overload { key: "timestamp_to_day_of_month_1_based_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_date, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_day_of_week() -> Function {
+    Function {
+        name: "getDayOfWeek",
+        overloads: &[
+            Overload { key: "timestamp_to_day_of_week", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_day_of_week, strict: true },
+            Overload { key: "timestamp_to_day_of_week_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_day_of_week, strict: true },
+        ],
+    }
+}
+
+// On durations, the accessors return the whole duration in the unit.
+
+pub fn new_get_hours() -> Function {
+    Function {
+        name: "getHours",
+        overloads: &[
+            Overload { key: "timestamp_to_hours", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_hours, strict: true },
+            Overload { key: "timestamp_to_hours_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_hours, strict: true },
+            Overload { key: "duration_to_hours", args: &[Ty::Duration], result: Ty::Int, func: duration_hours, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_minutes() -> Function {
+    Function {
+        name: "getMinutes",
+        overloads: &[
+            Overload { key: "timestamp_to_minutes", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_minutes, strict: true },
+            Overload { key: "timestamp_to_minutes_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_minutes, strict: true },
+            Overload { key: "duration_to_minutes", args: &[Ty::Duration], result: Ty::Int, func: duration_minutes, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_seconds() -> Function {
+    Function {
+        name: "getSeconds",
+        overloads: &[
+            Overload { key: "timestamp_to_seconds", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_seconds, strict: true },
+            Overload { key: "timestamp_to_seconds_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_seconds, strict: true },
+            Overload { key: "duration_to_seconds", args: &[Ty::Duration], result: Ty::Int, func: duration_seconds, strict: true },
+        ],
+    }
+}
+
+pub fn new_get_milliseconds() -> Function {
+    Function {
+        name: "getMilliseconds",
+        overloads: &[
+            Overload { key: "timestamp_to_milliseconds", args: &[Ty::Timestamp], result: Ty::Int, func: timestamp_milliseconds, strict: true },
+            Overload { key: "timestamp_to_milliseconds_with_tz", args: &[Ty::Timestamp, Ty::String], result: Ty::Int, func: timestamp_milliseconds, strict: true },
+            Overload { key: "duration_to_milliseconds", args: &[Ty::Duration], result: Ty::Int, func: duration_milliseconds, strict: true },
+        ],
+    }
+}
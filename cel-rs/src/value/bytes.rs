@@ -1,5 +1,5 @@
 use std::rc::Rc;
-use super::{ty::Ty, value::{Val, Value}};
+use super::{error::Error, ty::Ty, value::{Val, Value}};
 
 pub struct Bytes(Rc<Vec<u8>>);
 
@@ -33,4 +33,11 @@ impl Value for Bytes {
             (&self.0).cmp(ob).into()
         })
     }
-}
\ No newline at end of file
+
+    fn add(&self, other: &Val) -> Val {
+        match other.as_bytes() {
+            Some(o) => Val::new_bytes(Rc::new([self.0.as_slice(), o].concat())),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
+}
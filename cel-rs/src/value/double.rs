@@ -1,5 +1,7 @@
 
-use super::{ty::Ty, value::{Val, Value}};
+use std::cmp::Ordering;
+
+use super::{error::Error, ty::Ty, value::{Val, Value}};
 
 pub struct Double(f64);
 
@@ -9,6 +11,21 @@ impl Double {
     }
 }
 
+/// The double as an integer, if it has no fractional part.
+pub(crate) fn integral(d: f64) -> Option<i128> {
+    (d.fract() == 0.0 && d.abs() < 2f64.powi(127)).then_some(d as i128)
+}
+
+/// Orders a number, given by its integral value and its value as a double, against
+/// an int, uint or double: exactly when both are integral, as doubles otherwise.
+/// `None` if `other` is not a number, or either is NaN.
+pub(crate) fn compare_numbers(integral: Option<i128>, d: f64, other: &Val) -> Option<Ordering> {
+    match (integral, other.integral()) {
+        (Some(l), Some(r)) => Some(l.cmp(&r)),
+        _ => d.partial_cmp(&other.as_number()?),
+    }
+}
+
 impl Value for Double {
     fn ty(&self) -> super::ty::Ty {
         Ty::Double
@@ -19,19 +36,45 @@ impl Value for Double {
     }
 
     fn equals(&self, other: &Val) -> Val {
-        Val::new_bool(
-            other
-                .native_value()
-                .downcast_ref::<f64>()
-                .is_some_and(|f| f.eq(&self.0)),
-        )
+        match integral(self.0) {
+            Some(i) => Val::new_bool(other.integral() == Some(i)),
+            None => Val::new_bool(other.as_double().is_some_and(|f| f.eq(&self.0))),
+        }
     }
     
     fn compare(&self, other: &Val) -> Option<Val> {
-        let vl = other.native_value().downcast_ref::<f64>();
-        if vl.is_some() {
-            return (&self.0).partial_cmp(vl.unwrap()).map(|f| f.into())
+        compare_numbers(integral(self.0), self.0, other).map(Val::from)
+    }
+
+    fn add(&self, other: &Val) -> Val {
+        match other.as_double() {
+            Some(o) => Val::new_double(self.0 + o),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
+
+    fn subtract(&self, other: &Val) -> Val {
+        match other.as_double() {
+            Some(o) => Val::new_double(self.0 - o),
+            None => Error::no_matching_overload("_-_"),
+        }
+    }
+
+    fn multiply(&self, other: &Val) -> Val {
+        match other.as_double() {
+            Some(o) => Val::new_double(self.0 * o),
+            None => Error::no_matching_overload("_*_"),
         }
-        None
+    }
+
+    fn divide(&self, other: &Val) -> Val {
+        match other.as_double() {
+            Some(o) => Val::new_double(self.0 / o),
+            None => Error::no_matching_overload("_/_"),
+        }
+    }
+
+    fn negate(&self) -> Val {
+        Val::new_double(-self.0)
     }
 }
@@ -0,0 +1,122 @@
+use chrono::TimeDelta;
+
+use super::{error::Error, ty::Ty, value::Val, value::Value};
+
+/// A signed span of time with nanosecond precision, within the ±10000 years the
+/// CEL spec allows.
+pub struct Duration(TimeDelta);
+
+/// Whole seconds in 10000 years of 365.2425 days, the largest duration CEL allows.
+const MAX_SECONDS: i64 = 315_576_000_000;
+
+impl Duration {
+    pub fn new(d: TimeDelta) -> Self {
+        Self(d)
+    }
+}
+
+/// Checks that the duration is within the range CEL allows.
+pub fn checked_duration(d: TimeDelta) -> Val {
+    match d.num_seconds().abs() <= MAX_SECONDS {
+        true => Val::new(Duration::new(d)),
+        false => Error::overflow("duration"),
+    }
+}
+
+/// Parses a duration such as `1h30m`, `-1.5s` or `300ms`. The units are `h`, `m`,
+/// `s`, `ms`, `us` (or `µs`) and `ns`.
+pub fn parse_duration(s: &str) -> Option<TimeDelta> {
+    let (negative, mut rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if rest == "0" {
+        return Some(TimeDelta::zero());
+    }
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut nanos: i128 = 0;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let scale: i128 = match unit {
+            "h" => 3_600_000_000_000,
+            "m" => 60_000_000_000,
+            "s" => 1_000_000_000,
+            "ms" => 1_000_000,
+            "us" | "µs" => 1_000,
+            "ns" => 1,
+            _ => return None,
+        };
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        let whole: i128 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+        nanos = nanos.checked_add(whole.checked_mul(scale)?)?;
+        let mut place = scale;
+        for digit in fraction.chars() {
+            place /= 10;
+            nanos += digit.to_digit(10)? as i128 * place;
+        }
+        rest = tail;
+    }
+
+    let nanos = if negative { -nanos } else { nanos };
+    let seconds = i64::try_from(nanos / 1_000_000_000).ok()?;
+    TimeDelta::try_seconds(seconds)?.checked_add(&TimeDelta::nanoseconds((nanos % 1_000_000_000) as i64))
+}
+
+/// Renders the duration as seconds, e.g. `5400s` or `-1.5s`.
+pub fn format_duration(d: &TimeDelta) -> String {
+    let sign = if *d < TimeDelta::zero() { "-" } else { "" };
+    let (seconds, fraction) = (d.num_seconds().unsigned_abs(), d.subsec_nanos().unsigned_abs());
+    match format!("{:09}", fraction).trim_end_matches('0') {
+        "" => format!("{}{}s", sign, seconds),
+        fraction => format!("{}{}.{}s", sign, seconds, fraction),
+    }
+}
+
+impl Value for Duration {
+    fn ty(&self) -> Ty {
+        Ty::Duration
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        &self.0
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.as_duration().is_some_and(|d| d.eq(&self.0)))
+    }
+
+    fn compare(&self, other: &Val) -> Option<Val> {
+        other.as_duration().map(|d| self.0.cmp(d).into())
+    }
+
+    fn add(&self, other: &Val) -> Val {
+        if let Some(t) = other.as_timestamp() {
+            return t.checked_add_signed(self.0).map_or_else(|| Error::overflow("timestamp"), super::timestamp::checked_timestamp);
+        }
+        match other.as_duration() {
+            Some(d) => self.0.checked_add(d).map_or_else(|| Error::overflow("duration"), checked_duration),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
+
+    fn subtract(&self, other: &Val) -> Val {
+        match other.as_duration() {
+            Some(d) => self.0.checked_sub(d).map_or_else(|| Error::overflow("duration"), checked_duration),
+            None => Error::no_matching_overload("_-_"),
+        }
+    }
+
+    fn negate(&self) -> Val {
+        checked_duration(-self.0)
+    }
+}
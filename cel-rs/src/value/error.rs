@@ -1,35 +1,185 @@
 use core::fmt;
+use std::rc::Rc;
 
 use super::{
     ty::Ty,
     value::{Val, Value},
 };
+use crate::parser::{ExprId, Span};
 
-#[derive(Eq, PartialEq)]
+/// Machine-readable category of an [`Error`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum ErrorCode {
+    /// No more specific code applies, e.g. for errors raised by host functions.
+    Unspecified,
+    NoSuchKey,
+    NoSuchField,
+    NoMatchingOverload,
+    DivisionByZero,
+    Overflow,
+    InvalidArgument,
+    UnknownVariable,
+    UnknownFunction,
+    UnknownType,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Unspecified => "unspecified",
+            ErrorCode::NoSuchKey => "no_such_key",
+            ErrorCode::NoSuchField => "no_such_field",
+            ErrorCode::NoMatchingOverload => "no_matching_overload",
+            ErrorCode::DivisionByZero => "division_by_zero",
+            ErrorCode::Overflow => "overflow",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::UnknownVariable => "unknown_variable",
+            ErrorCode::UnknownFunction => "unknown_function",
+            ErrorCode::UnknownType => "unknown_type",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error value. The evaluator records the id and span of the expression that
+/// first produced it.
+#[derive(Debug, Clone)]
 pub struct Error {
-    id: Option<i64>,
-    error: String,
+    code: ErrorCode,
+    message: String,
+    id: Option<ExprId>,
+    span: Option<Span>,
+    source: Option<Rc<dyn std::error::Error>>,
 }
 
+/// Errors are compared by message only, so that an expected error can be written
+/// without knowing where it will be raised.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.message == other.message
+    }
+}
+
+impl Eq for Error {}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "error(id = {:?}, message = {})", self.id, self.error)
+        write!(f, "error(code = {}, id = {:?}, message = {})", self.code, self.id, self.message)
     }
 }
 
 impl Error {
     pub fn new(error: String) -> Val {
-        return Val::new(Self { id: None, error });
+        Self::with_code(ErrorCode::Unspecified, error)
+    }
+    pub fn with_code(code: ErrorCode, message: String) -> Val {
+        Val::new(Self {
+            code,
+            message,
+            id: None,
+            span: None,
+            source: None,
+        })
+    }
+    /// Wraps an error raised by host code, keeping it as the source of the error value.
+    pub fn with_source(code: ErrorCode, source: impl std::error::Error + 'static) -> Val {
+        Val::new(Self {
+            code,
+            message: source.to_string(),
+            id: None,
+            span: None,
+            source: Some(Rc::new(source)),
+        })
     }
     pub fn unimplemented(ty: Ty, f: &str) -> Val {
-        Self::new(format!("{} does not implement {}", ty.to_string(), f))
+        Self::with_code(
+            ErrorCode::NoMatchingOverload,
+            format!("{} does not implement {}", ty.to_string(), f),
+        )
+    }
+    pub fn no_matching_overload(function: &str) -> Val {
+        Self::with_code(
+            ErrorCode::NoMatchingOverload,
+            format!("no matching overload for '{}'", function),
+        )
+    }
+    pub fn no_such_key(key: &str) -> Val {
+        Self::with_code(ErrorCode::NoSuchKey, format!("no such key: {}", key))
+    }
+    pub fn no_such_field(field: &str) -> Val {
+        Self::with_code(ErrorCode::NoSuchField, format!("no such field: {}", field))
+    }
+    pub fn unsupported_selection(ty: Ty, field: &str) -> Val {
+        Self::with_code(
+            ErrorCode::NoMatchingOverload,
+            format!("type '{}' does not support field selection ('{}')", ty.to_string(), field),
+        )
+    }
+    pub fn overflow(operation: &str) -> Val {
+        Self::with_code(ErrorCode::Overflow, format!("{} overflow", operation))
+    }
+    pub fn division_by_zero() -> Val {
+        Self::with_code(ErrorCode::DivisionByZero, "division by zero".to_string())
+    }
+    pub fn modulus_by_zero() -> Val {
+        Self::with_code(ErrorCode::DivisionByZero, "modulus by zero".to_string())
+    }
+    pub fn index_out_of_bounds(index: i64) -> Val {
+        Self::with_code(ErrorCode::InvalidArgument, format!("index out of bounds: {}", index))
+    }
+    pub fn invalid_argument(message: String) -> Val {
+        Self::with_code(ErrorCode::InvalidArgument, message)
+    }
+    pub fn unknown_variable(name: &str) -> Val {
+        Self::with_code(ErrorCode::UnknownVariable, format!("unknown variable {}", name))
+    }
+    pub fn unknown_function(name: &str) -> Val {
+        Self::with_code(ErrorCode::UnknownFunction, format!("unknown function {}", name))
+    }
+    pub fn unknown_type(name: &str) -> Val {
+        Self::with_code(ErrorCode::UnknownType, format!("unknown type {}", name))
     }
     pub fn invalid_conversion(from_ty: Ty, to_ty: Ty) -> Val {
-        Self::new(format!(
-            "type {} could not be converted to {}",
-            from_ty.to_string(),
-            to_ty.to_string()
-        ))
+        Self::with_code(
+            ErrorCode::InvalidArgument,
+            format!(
+                "type {} could not be converted to {}",
+                from_ty.to_string(),
+                to_ty.to_string()
+            ),
+        )
+    }
+
+    /// The same error, attributed to the expression that raised it.
+    pub(crate) fn at(&self, id: ExprId, span: Span) -> Val {
+        Val::new(Self {
+            id: Some(id),
+            span: Some(span),
+            ..self.clone()
+        })
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    /// Id of the expression that raised the error.
+    pub fn id(&self) -> Option<ExprId> {
+        self.id
+    }
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+    /// The host error this error was raised from, if any.
+    pub fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_deref()
     }
 }
 
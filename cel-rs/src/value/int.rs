@@ -1,4 +1,4 @@
-use super::{ty::Ty, value::Val, value::Value};
+use super::{double::compare_numbers, error::Error, ty::Ty, value::Val, value::Value};
 
 pub struct Int(i64);
 
@@ -18,18 +18,51 @@ impl Value for Int {
     }
 
     fn equals(&self, other: &Val) -> Val {
-        Val::new_bool(
-            other
-                .native_value()
-                .downcast_ref::<i64>()
-                .is_some_and(|f| f.eq(&self.0)),
-        )
+        Val::new_bool(other.integral() == Some(self.0 as i128))
     }
 
     fn compare(&self, other: &Val) -> Option<Val> {
-        other
-            .native_value()
-            .downcast_ref::<i64>()
-            .map(|oi| (&self.0).cmp(oi).into())
+        compare_numbers(Some(self.0 as i128), self.0 as f64, other).map(Val::from)
+    }
+
+    fn add(&self, other: &Val) -> Val {
+        match other.as_int() {
+            Some(o) => self.0.checked_add(*o).map_or_else(|| Error::overflow("integer"), Val::new_int),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
+
+    fn subtract(&self, other: &Val) -> Val {
+        match other.as_int() {
+            Some(o) => self.0.checked_sub(*o).map_or_else(|| Error::overflow("integer"), Val::new_int),
+            None => Error::no_matching_overload("_-_"),
+        }
+    }
+
+    fn multiply(&self, other: &Val) -> Val {
+        match other.as_int() {
+            Some(o) => self.0.checked_mul(*o).map_or_else(|| Error::overflow("integer"), Val::new_int),
+            None => Error::no_matching_overload("_*_"),
+        }
+    }
+
+    fn divide(&self, other: &Val) -> Val {
+        match other.as_int() {
+            Some(0) => Error::division_by_zero(),
+            Some(o) => self.0.checked_div(*o).map_or_else(|| Error::overflow("integer"), Val::new_int),
+            None => Error::no_matching_overload("_/_"),
+        }
+    }
+
+    fn modulo(&self, other: &Val) -> Val {
+        match other.as_int() {
+            Some(0) => Error::modulus_by_zero(),
+            Some(o) => self.0.checked_rem(*o).map_or_else(|| Error::overflow("integer"), Val::new_int),
+            None => Error::no_matching_overload("_%_"),
+        }
+    }
+
+    fn negate(&self) -> Val {
+        self.0.checked_neg().map_or_else(|| Error::overflow("integer"), Val::new_int)
     }
 }
@@ -0,0 +1,52 @@
+use super::error::Error;
+use super::ty::Ty;
+use crate::{Val, Value};
+use std::rc::Rc;
+
+pub struct List(Rc<Vec<Val>>);
+
+impl List {
+    pub fn new(l: Rc<Vec<Val>>) -> Self {
+        Self(l)
+    }
+}
+
+impl Value for List {
+    fn ty(&self) -> Ty {
+        Ty::List
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        &self.0
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.as_list().is_some_and(|other| {
+            other.len() == self.0.len() && self.0.iter().zip(other.iter()).all(|(a, b)| a == b)
+        }))
+    }
+
+    fn add(&self, other: &Val) -> Val {
+        match other.as_list() {
+            Some(other) => Val::new_list(Rc::new(self.0.iter().chain(other.iter()).cloned().collect())),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
+
+    fn contains(&self, elem: &Val) -> Val {
+        Val::new_bool(self.0.iter().any(|v| v == elem))
+    }
+
+    fn index(&self, index: &Val) -> Val {
+        let i = match (index.as_int(), index.as_uint()) {
+            (Some(i), _) => *i,
+            (_, Some(u)) => i64::try_from(*u).unwrap_or(i64::MAX),
+            _ => return Error::no_matching_overload("_[_]"),
+        };
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| self.0.get(i))
+            .cloned()
+            .unwrap_or_else(|| Error::index_out_of_bounds(i))
+    }
+}
@@ -1,3 +1,4 @@
+use super::error::Error;
 use super::ty::Ty;
 use crate::{Val, Value};
 use std::{collections::HashMap, rc::Rc};
@@ -10,6 +11,23 @@ impl Map {
     }
 }
 
+/// Renders a map key the way it would be written in an expression.
+pub(crate) fn describe_key(key: &Val) -> String {
+    if let Some(s) = key.as_string() {
+        return s.to_string();
+    }
+    if let Some(i) = key.as_int() {
+        return i.to_string();
+    }
+    if let Some(u) = key.as_uint() {
+        return format!("{}u", u);
+    }
+    if let Some(b) = key.as_bool() {
+        return b.to_string();
+    }
+    format!("{:?}", key)
+}
+
 impl Value for Map {
     fn ty(&self) -> super::ty::Ty {
         Ty::Map
@@ -31,8 +49,7 @@ impl Value for Map {
                 for (k, v) in self.0.iter() {
                     let ov = other.get(k);
                     if let Some(ov) = ov {
-                        // TODO: use value.equals once all types support it.
-                        if !ov.compare(v).is_some_and(|o| o.as_int() == Some(&0)) {
+                        if ov != v {
                             return Val::new_bool(false);
                         }
                     } else {
@@ -44,4 +61,23 @@ impl Value for Map {
             })
             .unwrap_or(Val::new_bool(false))
     }
+
+    fn contains(&self, elem: &Val) -> Val {
+        Val::new_bool(self.0.contains_key(elem))
+    }
+
+    fn index(&self, index: &Val) -> Val {
+        self.0
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Error::no_such_key(&describe_key(index)))
+    }
+
+    fn select(&self, field: &str) -> Val {
+        self.index(&Val::new_string(field))
+    }
+
+    fn has_field(&self, field: &str) -> Val {
+        self.contains(&Val::new_string(field))
+    }
 }
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor, ReflectMessage, Value as ProtoValue};
+
+use super::error::Error;
+use super::ty::Ty;
+use super::value::{Val, Value};
+
+/// A protobuf message. Fields read from it are converted to CEL values, with
+/// well-known types such as `google.protobuf.Timestamp` and the wrappers
+/// becoming their CEL counterparts.
+pub struct Message(DynamicMessage);
+
+impl Message {
+    pub fn new(m: DynamicMessage) -> Self {
+        Self(m)
+    }
+}
+
+impl Value for Message {
+    fn ty(&self) -> Ty {
+        Ty::Message
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        &self.0
+    }
+
+    /// Messages are equal when they have the same type, set the same fields and
+    /// the fields are equal as CEL values.
+    fn equals(&self, other: &Val) -> Val {
+        let Some(other) = other.as_message() else {
+            return Val::new_bool(false);
+        };
+        let desc = self.0.descriptor();
+        if desc.full_name() != other.descriptor().full_name() {
+            return Val::new_bool(false);
+        }
+        let equal = desc.fields().all(|field| match (self.0.has_field(&field), other.has_field(&field)) {
+            (true, true) => field_value(&self.0, &field) == field_value(other, &field),
+            (set, other_set) => set == other_set,
+        });
+        Val::new_bool(equal)
+    }
+
+    fn select(&self, field: &str) -> Val {
+        match self.0.descriptor().get_field_by_name(field) {
+            Some(field) => field_value(&self.0, &field),
+            None => Error::no_such_field(field),
+        }
+    }
+
+    /// Repeated and map fields are present when not empty. Other fields follow the
+    /// presence rules of the message's syntax: proto3 scalars without `optional`
+    /// are present when not zero.
+    fn has_field(&self, field: &str) -> Val {
+        match self.0.descriptor().get_field_by_name(field) {
+            Some(field) => Val::new_bool(self.0.has_field(&field)),
+            None => Error::no_such_field(field),
+        }
+    }
+}
+
+/// The CEL value of a field. Unset wrapper and `google.protobuf.Value` fields
+/// read as `null` rather than as their default.
+pub(crate) fn field_value(msg: &DynamicMessage, field: &FieldDescriptor) -> Val {
+    if let Kind::Message(desc) = field.kind() {
+        if !field.is_list() && !field.is_map() && !msg.has_field(field) && reads_as_null(desc.full_name()) {
+            return Val::new_null();
+        }
+    }
+    from_proto(&msg.get_field(field))
+}
+
+fn reads_as_null(full_name: &str) -> bool {
+    matches!(
+        full_name,
+        "google.protobuf.BoolValue"
+            | "google.protobuf.BytesValue"
+            | "google.protobuf.DoubleValue"
+            | "google.protobuf.FloatValue"
+            | "google.protobuf.Int32Value"
+            | "google.protobuf.Int64Value"
+            | "google.protobuf.StringValue"
+            | "google.protobuf.UInt32Value"
+            | "google.protobuf.UInt64Value"
+            | "google.protobuf.Value"
+    )
+}
+
+/// Converts a protobuf value: 32-bit integers widen to `int` and `uint`, floats to
+/// `double`, enums to `int`, repeated fields to lists and map fields to maps.
+pub(crate) fn from_proto(value: &ProtoValue) -> Val {
+    match value {
+        ProtoValue::Bool(b) => Val::new_bool(*b),
+        ProtoValue::I32(i) => Val::new_int(*i as i64),
+        ProtoValue::I64(i) => Val::new_int(*i),
+        ProtoValue::U32(u) => Val::new_uint(*u as u64),
+        ProtoValue::U64(u) => Val::new_uint(*u),
+        ProtoValue::F32(f) => Val::new_double(*f as f64),
+        ProtoValue::F64(f) => Val::new_double(*f),
+        ProtoValue::String(s) => Val::new_string(s),
+        ProtoValue::Bytes(b) => Val::new_bytes(Rc::new(b.to_vec())),
+        ProtoValue::EnumNumber(n) => Val::new_int(*n as i64),
+        ProtoValue::Message(m) => from_message(m),
+        ProtoValue::List(l) => Val::new_list(Rc::new(l.iter().map(from_proto).collect())),
+        ProtoValue::Map(m) => {
+            let map: HashMap<Val, Val> = m.iter().map(|(k, v)| (from_map_key(k), from_proto(v))).collect();
+            Val::new_map(Rc::new(map))
+        }
+    }
+}
+
+fn from_map_key(key: &MapKey) -> Val {
+    match key {
+        MapKey::Bool(b) => Val::new_bool(*b),
+        MapKey::I32(i) => Val::new_int(*i as i64),
+        MapKey::I64(i) => Val::new_int(*i),
+        MapKey::U32(u) => Val::new_uint(*u as u64),
+        MapKey::U64(u) => Val::new_uint(*u),
+        MapKey::String(s) => Val::new_string(s),
+    }
+}
+
+/// Converts a message, unwrapping the well-known types.
+pub(crate) fn from_message(m: &DynamicMessage) -> Val {
+    let field = |name: &str| m.get_field_by_name(name).unwrap().into_owned();
+    match m.descriptor().full_name() {
+        "google.protobuf.Timestamp" => match (field("seconds"), field("nanos")) {
+            (ProtoValue::I64(s), ProtoValue::I32(n)) => Val::new_timestamp(s, n as u32),
+            _ => unreachable!("malformed google.protobuf.Timestamp"),
+        },
+        "google.protobuf.Duration" => match (field("seconds"), field("nanos")) {
+            (ProtoValue::I64(s), ProtoValue::I32(n)) => Val::new_duration(s, n),
+            _ => unreachable!("malformed google.protobuf.Duration"),
+        },
+        name if reads_as_null(name) && name != "google.protobuf.Value" => from_proto(&field("value")),
+        "google.protobuf.Struct" => from_proto(&field("fields")),
+        "google.protobuf.ListValue" => from_proto(&field("values")),
+        "google.protobuf.Value" => {
+            let kind = m.descriptor().fields().find(|f| m.has_field(f));
+            match kind {
+                Some(f) if f.name() != "null_value" => from_proto(&m.get_field(&f)),
+                _ => Val::new_null(),
+            }
+        }
+        _ => Val::new(Message::new(m.clone())),
+    }
+}
+
+/// Converts a CEL value for assignment to a field, the inverse of [`field_value`].
+pub(crate) fn to_field_value(val: &Val, field: &FieldDescriptor) -> Result<ProtoValue, Val> {
+    if field.is_map() {
+        let Kind::Message(entry) = field.kind() else { unreachable!("map fields hold entries") };
+        let (key_kind, value_kind) = (entry.map_entry_key_field().kind(), entry.map_entry_value_field().kind());
+        let map = val.as_map().ok_or_else(|| mismatch(&field.kind(), val))?;
+        let entries = map
+            .iter()
+            .map(|(k, v)| Ok((to_map_key(k, &key_kind)?, to_proto(v, &value_kind)?)))
+            .collect::<Result<_, Val>>()?;
+        return Ok(ProtoValue::Map(entries));
+    }
+    if field.is_list() {
+        let list = val.as_list().ok_or_else(|| mismatch(&field.kind(), val))?;
+        let elems = list.iter().map(|e| to_proto(e, &field.kind())).collect::<Result<_, _>>()?;
+        return Ok(ProtoValue::List(elems));
+    }
+    to_proto(val, &field.kind())
+}
+
+fn mismatch(kind: &Kind, val: &Val) -> Val {
+    let expected = match kind {
+        Kind::Message(desc) => desc.full_name().to_string(),
+        Kind::Enum(desc) => desc.full_name().to_string(),
+        other => format!("{:?}", other).to_lowercase(),
+    };
+    Error::invalid_argument(format!("field type mismatch: expected {}, got {}", expected, val.ty().to_string()))
+}
+
+fn to_map_key(key: &Val, kind: &Kind) -> Result<MapKey, Val> {
+    Ok(match to_proto(key, kind)? {
+        ProtoValue::Bool(b) => MapKey::Bool(b),
+        ProtoValue::I32(i) => MapKey::I32(i),
+        ProtoValue::I64(i) => MapKey::I64(i),
+        ProtoValue::U32(u) => MapKey::U32(u),
+        ProtoValue::U64(u) => MapKey::U64(u),
+        ProtoValue::String(s) => MapKey::String(s),
+        _ => return Err(mismatch(kind, key)),
+    })
+}
+
+/// Converts a CEL value to a protobuf value of the given kind, checking the range
+/// of 32-bit integers.
+pub(crate) fn to_proto(val: &Val, kind: &Kind) -> Result<ProtoValue, Val> {
+    let converted = match kind {
+        Kind::Double => val.as_double().map(|d| ProtoValue::F64(*d)),
+        Kind::Float => val.as_double().map(|d| ProtoValue::F32(*d as f32)),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => val.as_int().map(|i| ProtoValue::I64(*i)),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => match val.as_int() {
+            Some(i) => Some(ProtoValue::I32(i32::try_from(*i).map_err(|_| Error::overflow("int32"))?)),
+            None => None,
+        },
+        Kind::Uint64 | Kind::Fixed64 => val.as_uint().map(|u| ProtoValue::U64(*u)),
+        Kind::Uint32 | Kind::Fixed32 => match val.as_uint() {
+            Some(u) => Some(ProtoValue::U32(u32::try_from(*u).map_err(|_| Error::overflow("uint32"))?)),
+            None => None,
+        },
+        Kind::Bool => val.as_bool().map(|b| ProtoValue::Bool(*b)),
+        Kind::String => val.as_string().map(|s| ProtoValue::String(s.to_string())),
+        Kind::Bytes => val.as_bytes().map(|b| ProtoValue::Bytes(b.to_vec().into())),
+        Kind::Enum(_) => match val.as_int() {
+            Some(i) => Some(ProtoValue::EnumNumber(i32::try_from(*i).map_err(|_| Error::overflow("enum"))?)),
+            None => None,
+        },
+        Kind::Message(desc) => to_message(val, desc)?.map(ProtoValue::Message),
+    };
+    converted.ok_or_else(|| mismatch(kind, val))
+}
+
+/// Converts a CEL value to a message, wrapping it in a well-known type when the
+/// message is one. `None` means the value does not fit the message type.
+fn to_message(val: &Val, desc: &MessageDescriptor) -> Result<Option<DynamicMessage>, Val> {
+    let mut m = DynamicMessage::new(desc.clone());
+    match desc.full_name() {
+        "google.protobuf.Timestamp" => {
+            let Some(t) = val.as_timestamp() else { return Ok(None) };
+            m.set_field_by_name("seconds", ProtoValue::I64(t.timestamp()));
+            m.set_field_by_name("nanos", ProtoValue::I32(t.timestamp_subsec_nanos() as i32));
+        }
+        "google.protobuf.Duration" => {
+            let Some(d) = val.as_duration() else { return Ok(None) };
+            m.set_field_by_name("seconds", ProtoValue::I64(d.num_seconds()));
+            m.set_field_by_name("nanos", ProtoValue::I32(d.subsec_nanos()));
+        }
+        name if reads_as_null(name) && name != "google.protobuf.Value" => {
+            let inner = desc.get_field_by_name("value").unwrap();
+            m.set_field(&inner, to_proto(val, &inner.kind())?);
+        }
+        "google.protobuf.Value" => {
+            let (field, value) = if val.ty() == Ty::Null {
+                ("null_value", ProtoValue::EnumNumber(0))
+            } else if let Some(b) = val.as_bool() {
+                ("bool_value", ProtoValue::Bool(*b))
+            } else if let Some(s) = val.as_string() {
+                ("string_value", ProtoValue::String(s.to_string()))
+            } else if let Some(d) = val.as_double() {
+                ("number_value", ProtoValue::F64(*d))
+            } else if let Some(i) = val.integral() {
+                ("number_value", ProtoValue::F64(i as f64))
+            } else if val.as_list().is_some() {
+                ("list_value", to_proto(val, &field_kind(desc, "list_value"))?)
+            } else if val.as_map().is_some() {
+                ("struct_value", to_proto(val, &field_kind(desc, "struct_value"))?)
+            } else {
+                return Ok(None);
+            };
+            m.set_field_by_name(field, value);
+        }
+        "google.protobuf.Struct" => {
+            let fields = desc.get_field_by_name("fields").unwrap();
+            m.set_field(&fields, to_field_value(val, &fields)?);
+        }
+        "google.protobuf.ListValue" => {
+            let values = desc.get_field_by_name("values").unwrap();
+            m.set_field(&values, to_field_value(val, &values)?);
+        }
+        name => match val.as_message() {
+            Some(other) if other.descriptor().full_name() == name => return Ok(Some(other.clone())),
+            _ => return Ok(None),
+        },
+    }
+    Ok(Some(m))
+}
+
+fn field_kind(desc: &MessageDescriptor, field: &str) -> Kind {
+    desc.get_field_by_name(field).unwrap().kind()
+}
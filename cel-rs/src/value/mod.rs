@@ -13,3 +13,9 @@ pub mod double;
 pub mod uint;
 pub mod int;
 pub mod map;
+pub mod list;
+pub mod timestamp;
+pub mod duration;
+pub mod optional;
+pub mod message;
+pub mod unknown;
@@ -0,0 +1,25 @@
+use super::ty::Ty;
+use super::value::{Val, Value};
+
+/// A value that may be absent, as returned by `optional.of(x)` or `list.first()`.
+pub struct Optional(Option<Val>);
+
+impl Optional {
+    pub fn new(v: Option<Val>) -> Self {
+        Self(v)
+    }
+}
+
+impl Value for Optional {
+    fn ty(&self) -> Ty {
+        Ty::Optional
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        &self.0
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.as_optional().is_some_and(|other| other == &self.0))
+    }
+}
@@ -1,5 +1,6 @@
 use std::string::String as StdString;
 
+use super::error::Error;
 use super::ty::Ty;
 use super::value::{Val, Value};
 
@@ -33,4 +34,11 @@ impl Value for String {
             Val::from((&self.0).cmp(oths))
         })
     }
+
+    fn add(&self, other: &Val) -> Val {
+        match other.as_string() {
+            Some(o) => Val::new_string(format!("{}{}", self.0, o)),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
 }
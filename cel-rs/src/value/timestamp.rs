@@ -0,0 +1,67 @@
+use chrono::{DateTime, Datelike, SecondsFormat, Utc};
+
+use super::{error::Error, ty::Ty, value::Val, value::Value};
+
+/// A point in time with nanosecond precision, between the years 1 and 9999.
+pub struct Timestamp(DateTime<Utc>);
+
+impl Timestamp {
+    pub fn new(t: DateTime<Utc>) -> Self {
+        Self(t)
+    }
+}
+
+/// Checks that the timestamp is within the range CEL allows.
+pub fn checked_timestamp(t: DateTime<Utc>) -> Val {
+    if (1..=9999).contains(&t.year()) {
+        Val::new(Timestamp::new(t))
+    } else {
+        Error::overflow("timestamp")
+    }
+}
+
+/// Renders the timestamp as RFC 3339, in UTC and with no trailing zeros in the
+/// fractional seconds.
+pub fn format_timestamp(t: &DateTime<Utc>) -> String {
+    let s = t.to_rfc3339_opts(SecondsFormat::Nanos, true);
+    let (datetime, fraction) = s.trim_end_matches('Z').split_once('.').unwrap();
+    match fraction.trim_end_matches('0') {
+        "" => format!("{}Z", datetime),
+        fraction => format!("{}.{}Z", datetime, fraction),
+    }
+}
+
+impl Value for Timestamp {
+    fn ty(&self) -> Ty {
+        Ty::Timestamp
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        &self.0
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.as_timestamp().is_some_and(|t| t.eq(&self.0)))
+    }
+
+    fn compare(&self, other: &Val) -> Option<Val> {
+        other.as_timestamp().map(|t| self.0.cmp(t).into())
+    }
+
+    fn add(&self, other: &Val) -> Val {
+        match other.as_duration() {
+            Some(d) => self.0.checked_add_signed(*d).map_or_else(|| Error::overflow("timestamp"), checked_timestamp),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
+
+    fn subtract(&self, other: &Val) -> Val {
+        if let Some(d) = other.as_duration() {
+            return self.0.checked_sub_signed(*d).map_or_else(|| Error::overflow("timestamp"), checked_timestamp);
+        }
+        match other.as_timestamp() {
+            Some(t) => super::duration::checked_duration(self.0.signed_duration_since(*t)),
+            None => Error::no_matching_overload("_-_"),
+        }
+    }
+}
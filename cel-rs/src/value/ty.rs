@@ -3,7 +3,7 @@ use crate::Val;
 use super::value::Value;
 
 // https://github.com/google/cel-spec/blob/master/doc/langdef.md#values
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
 pub enum Ty {
     Int,   
     UInt,
@@ -14,6 +14,13 @@ pub enum Ty {
     List,
     Map,
     Null,
+    Timestamp,
+    Duration,
+    Optional,
+    /// A protobuf message.
+    Message,
+    /// A type defined by an extension, such as `net.IP`, named by its CEL type name.
+    Opaque(&'static str),
     // these should be here?
     Type,
     Unknown,
@@ -34,6 +41,11 @@ impl ToString for Ty {
             Ty::List => "list",
             Ty::Map => "map",
             Ty::Null => "null_type",
+            Ty::Timestamp => "google.protobuf.Timestamp",
+            Ty::Duration => "google.protobuf.Duration",
+            Ty::Optional => "optional_type",
+            Ty::Message => "message",
+            Ty::Opaque(name) => name,
             Ty::Type => "type",
             Ty::Unknown => "unknown",
             Ty::Error => "error",
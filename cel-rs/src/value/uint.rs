@@ -1,4 +1,6 @@
 use super::{
+    double::compare_numbers,
+    error::Error,
     ty::Ty,
     value::{Val, Value},
 };
@@ -21,18 +23,47 @@ impl Value for Uint {
     }
 
     fn equals(&self, other: &Val) -> Val {
-        Val::new_bool(
-            other
-                .native_value()
-                .downcast_ref::<u64>()
-                .is_some_and(|f| f.eq(&self.0)),
-        )
+        Val::new_bool(other.integral() == Some(self.0 as i128))
     }
 
     fn compare(&self, other: &Val) -> Option<Val> {
-        other
-            .native_value()
-            .downcast_ref::<u64>()
-            .map(|oui| Val::from((&self.0).cmp(oui)))
+        compare_numbers(Some(self.0 as i128), self.0 as f64, other).map(Val::from)
+    }
+
+    fn add(&self, other: &Val) -> Val {
+        match other.as_uint() {
+            Some(o) => self.0.checked_add(*o).map_or_else(|| Error::overflow("unsigned integer"), Val::new_uint),
+            None => Error::no_matching_overload("_+_"),
+        }
+    }
+
+    fn subtract(&self, other: &Val) -> Val {
+        match other.as_uint() {
+            Some(o) => self.0.checked_sub(*o).map_or_else(|| Error::overflow("unsigned integer"), Val::new_uint),
+            None => Error::no_matching_overload("_-_"),
+        }
+    }
+
+    fn multiply(&self, other: &Val) -> Val {
+        match other.as_uint() {
+            Some(o) => self.0.checked_mul(*o).map_or_else(|| Error::overflow("unsigned integer"), Val::new_uint),
+            None => Error::no_matching_overload("_*_"),
+        }
+    }
+
+    fn divide(&self, other: &Val) -> Val {
+        match other.as_uint() {
+            Some(0) => Error::division_by_zero(),
+            Some(o) => Val::new_uint(self.0 / o),
+            None => Error::no_matching_overload("_/_"),
+        }
+    }
+
+    fn modulo(&self, other: &Val) -> Val {
+        match other.as_uint() {
+            Some(0) => Error::modulus_by_zero(),
+            Some(o) => Val::new_uint(self.0 % o),
+            None => Error::no_matching_overload("_%_"),
+        }
     }
 }
@@ -0,0 +1,61 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::ty::Ty;
+use crate::parser::ExprId;
+use crate::{Val, Value};
+
+/// A value that could not be computed because it depends on attributes marked as
+/// unknown. Holds the ids of the attribute expressions responsible.
+#[derive(Eq, PartialEq)]
+pub struct Unknown(BTreeSet<ExprId>);
+
+impl Unknown {
+    pub fn new(id: ExprId) -> Self {
+        Self(BTreeSet::from([id]))
+    }
+
+    /// Ids of the unknown attribute expressions, in ascending order.
+    pub fn ids(&self) -> impl Iterator<Item = ExprId> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl fmt::Debug for Unknown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// What a strict operation over `args` evaluates to without being performed: the
+/// union of all unknown arguments, otherwise the first error.
+pub fn propagate<'a>(args: impl IntoIterator<Item = &'a Val>) -> Option<Val> {
+    let mut ids = BTreeSet::new();
+    let mut error = None;
+    for arg in args {
+        match arg.ty() {
+            Ty::Unknown => ids.extend(arg.as_unknown().unwrap().ids()),
+            Ty::Error if error.is_none() => error = Some(arg.clone()),
+            _ => {}
+        }
+    }
+    if ids.is_empty() {
+        error
+    } else {
+        Some(Val::new(Unknown(ids)))
+    }
+}
+
+impl Value for Unknown {
+    fn ty(&self) -> Ty {
+        Ty::Unknown
+    }
+
+    fn native_value(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn equals(&self, other: &Val) -> Val {
+        Val::new_bool(other.as_unknown().is_some_and(|o| o == self))
+    }
+}
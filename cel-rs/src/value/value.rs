@@ -2,16 +2,23 @@ use std::cmp;
 use std::collections::HashMap;
 use std::{fmt, rc::Rc};
 
+use crate::parser::ExprId;
 use crate::value::{error::Error, ty::Ty};
 
 use super::bool::Bool;
 use super::bytes::Bytes;
-use super::double::Double;
+use super::double::{self, Double};
+use super::duration::{checked_duration, format_duration};
 use super::int::Int;
+use super::list::List;
 use super::map::Map;
+use super::message;
 use super::null::Null;
+use super::optional::Optional;
 use super::string::String as CELString;
+use super::timestamp::{checked_timestamp, format_timestamp};
 use super::uint::Uint;
+use super::unknown::Unknown;
 
 pub trait Value {
     fn ty(&self) -> Ty;
@@ -26,13 +33,57 @@ pub trait Value {
 
     fn native_value(&self) -> &dyn std::any::Any;
 
-    fn compare(&self, other: &Val) -> Option<Val> {
-        unimplemented!("compare {:?} {:?}", self.ty(), other.ty())
+    fn compare(&self, _other: &Val) -> Option<Val> {
+        None
     }
 
     fn equals(&self, other: &Val) -> Val {
         unimplemented!("equals {:?} {:?}", self.ty(), other.ty())
     }
+
+    fn add(&self, _other: &Val) -> Val {
+        Error::no_matching_overload("_+_")
+    }
+
+    fn subtract(&self, _other: &Val) -> Val {
+        Error::no_matching_overload("_-_")
+    }
+
+    fn multiply(&self, _other: &Val) -> Val {
+        Error::no_matching_overload("_*_")
+    }
+
+    fn divide(&self, _other: &Val) -> Val {
+        Error::no_matching_overload("_/_")
+    }
+
+    fn modulo(&self, _other: &Val) -> Val {
+        Error::no_matching_overload("_%_")
+    }
+
+    fn negate(&self) -> Val {
+        Error::no_matching_overload("-_")
+    }
+
+    /// Implements `elem in self`.
+    fn contains(&self, _elem: &Val) -> Val {
+        Error::no_matching_overload("@in")
+    }
+
+    /// Implements `self[index]`.
+    fn index(&self, _index: &Val) -> Val {
+        Error::no_matching_overload("_[_]")
+    }
+
+    /// Implements `self.field`.
+    fn select(&self, field: &str) -> Val {
+        Error::unsupported_selection(self.ty(), field)
+    }
+
+    /// Implements `has(self.field)`.
+    fn has_field(&self, _field: &str) -> Val {
+        Error::no_matching_overload("has")
+    }
 }
 
 pub struct Val(Rc<dyn Value>);
@@ -43,9 +94,30 @@ impl cmp::PartialOrd for Val {
     }
 }
 
+/// Consistent with CEL's heterogeneous equality: `1`, `1u` and `1.0` hash alike.
 impl std::hash::Hash for Val {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        state.write(format!("TODO:{:?}", &self).as_bytes());
+        if let Some(i) = self.integral() {
+            return i.hash(state);
+        }
+        self.ty().to_string().hash(state);
+        if let Some(d) = self.as_double() {
+            d.to_bits().hash(state);
+        } else if let Some(s) = self.as_string() {
+            s.hash(state);
+        } else if let Some(b) = self.as_bytes() {
+            b.hash(state);
+        } else if let Some(b) = self.as_bool() {
+            b.hash(state);
+        } else if let Some(t) = self.as_timestamp() {
+            t.hash(state);
+        } else if let Some(d) = self.as_duration() {
+            d.hash(state);
+        } else if let Some(l) = self.as_list() {
+            l.hash(state);
+        } else if let Some(m) = self.as_map() {
+            m.len().hash(state);
+        }
     }
 }
 
@@ -88,15 +160,20 @@ impl fmt::Debug for Val {
                 ", value = {:?}",
                 self.native_value().downcast_ref::<Rc<Vec<u8>>>().unwrap()
             ),
-            Ty::List => write!(f, ", value = TODO"),
+            Ty::List => write!(f, ", value = {:?}", self.as_list().unwrap()),
             Ty::Map => write!(f, ", value = {:?}",  self.native_value().downcast_ref::<Rc<HashMap<Val, Val>>>().unwrap()),
             Ty::Null => write!(f, ", value = null"),
+            Ty::Timestamp => write!(f, ", value = {}", format_timestamp(self.as_timestamp().unwrap())),
+            Ty::Duration => write!(f, ", value = {}", format_duration(self.as_duration().unwrap())),
+            Ty::Optional => write!(f, ", value = {:?}", self.as_optional().unwrap()),
+            Ty::Message => write!(f, ", value = {:?}", self.as_message().unwrap()),
+            Ty::Opaque(_) => Ok(()),
             Ty::Type => write!(
                 f,
                 ", value = {:?}",
                 self.native_value().downcast_ref::<Ty>().unwrap()
             ),
-            Ty::Unknown => write!(f, ", value = ?"),
+            Ty::Unknown => write!(f, ", value = {:?}", self.as_unknown().unwrap()),
             Ty::Error => write!(
                 f,
                 ", value = {}",
@@ -123,7 +200,7 @@ impl Val {
         Self::new(Bool::new(b))
     }
     pub fn new_error(e: String) -> Self {
-        Self::new(Error::new(e))
+        Error::new(e)
     }
     pub fn new_string(s: impl ToString) -> Self {
         Self::new(CELString::new(s.to_string()))
@@ -147,7 +224,54 @@ impl Val {
         Self::new(Map::new(h))
     }
     pub fn new_list(b: Rc<Vec<Val>>) -> Self {
-        Self::new(Null::new())
+        Self::new(List::new(b))
+    }
+    /// A timestamp `seconds` and `nanos` after the Unix epoch, or an error value if it
+    /// falls outside the years 1 to 9999.
+    pub fn new_timestamp(seconds: i64, nanos: u32) -> Self {
+        match chrono::DateTime::from_timestamp(seconds, nanos) {
+            Some(t) => checked_timestamp(t),
+            None => Error::overflow("timestamp"),
+        }
+    }
+    /// A duration of `seconds` plus `nanos`, or an error value if it is longer than
+    /// the 10000 years CEL allows.
+    pub fn new_duration(seconds: i64, nanos: i32) -> Self {
+        match chrono::TimeDelta::try_seconds(seconds)
+            .and_then(|d| d.checked_add(&chrono::TimeDelta::nanoseconds(nanos as i64)))
+        {
+            Some(d) => checked_duration(d),
+            None => Error::overflow("duration"),
+        }
+    }
+    pub fn new_optional(v: Option<Val>) -> Self {
+        Self::new(Optional::new(v))
+    }
+    /// A protobuf message. Well-known types become their CEL counterparts, so a
+    /// `google.protobuf.Timestamp` is a timestamp.
+    pub fn new_message(m: prost_reflect::DynamicMessage) -> Self {
+        message::from_message(&m)
+    }
+    pub fn new_unknown(id: ExprId) -> Self {
+        Self::new(Unknown::new(id))
+    }
+    /// The value of an int, a uint or a double without a fractional part, so that
+    /// numbers of different types can be compared for equality.
+    pub(crate) fn integral(&self) -> Option<i128> {
+        if let Some(i) = self.as_int() {
+            return Some(*i as i128);
+        }
+        if let Some(u) = self.as_uint() {
+            return Some(*u as i128);
+        }
+        self.as_double().and_then(|d| double::integral(*d))
+    }
+    /// The value of an int, a uint or a double, as a double.
+    pub(crate) fn as_number(&self) -> Option<f64> {
+        self.as_double()
+            .copied()
+            .or_else(|| self.as_int().map(|i| *i as f64))
+            .or_else(|| self.as_uint().map(|u| *u as f64))
     }
     pub fn as_bool(&self) -> Option<&bool> {
         return self.0.native_value().downcast_ref::<bool>();
@@ -155,6 +279,42 @@ impl Val {
     pub fn as_int(&self) -> Option<&i64> {
         return self.0.native_value().downcast_ref::<i64>();
     }
+    pub fn as_uint(&self) -> Option<&u64> {
+        self.0.native_value().downcast_ref::<u64>()
+    }
+    pub fn as_double(&self) -> Option<&f64> {
+        self.0.native_value().downcast_ref::<f64>()
+    }
+    pub fn as_string(&self) -> Option<&str> {
+        self.0.native_value().downcast_ref::<std::string::String>().map(|s| s.as_str())
+    }
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.0.native_value().downcast_ref::<Rc<Vec<u8>>>().map(|b| b.as_slice())
+    }
+    pub fn as_list(&self) -> Option<&[Val]> {
+        self.0.native_value().downcast_ref::<Rc<Vec<Val>>>().map(|l| l.as_slice())
+    }
+    pub fn as_map(&self) -> Option<&HashMap<Val, Val>> {
+        self.0.native_value().downcast_ref::<Rc<HashMap<Val, Val>>>().map(|m| m.as_ref())
+    }
+    pub fn as_timestamp(&self) -> Option<&chrono::DateTime<chrono::Utc>> {
+        self.0.native_value().downcast_ref::<chrono::DateTime<chrono::Utc>>()
+    }
+    pub fn as_duration(&self) -> Option<&chrono::TimeDelta> {
+        self.0.native_value().downcast_ref::<chrono::TimeDelta>()
+    }
+    pub fn as_optional(&self) -> Option<&Option<Val>> {
+        self.0.native_value().downcast_ref::<Option<Val>>()
+    }
+    pub fn as_message(&self) -> Option<&prost_reflect::DynamicMessage> {
+        self.0.native_value().downcast_ref::<prost_reflect::DynamicMessage>()
+    }
+    pub fn as_error(&self) -> Option<&Error> {
+        self.0.native_value().downcast_ref::<Error>()
+    }
+    pub fn as_unknown(&self) -> Option<&Unknown> {
+        self.0.native_value().downcast_ref::<Unknown>()
+    }
 }
 
 impl Value for Val {
@@ -187,4 +347,54 @@ impl Value for Val {
     fn to_type(&self, ty: Ty) -> Val {
         self.0.to_type(ty)
     }
+
+    #[inline]
+    fn add(&self, other: &Val) -> Val {
+        self.0.add(other)
+    }
+
+    #[inline]
+    fn subtract(&self, other: &Val) -> Val {
+        self.0.subtract(other)
+    }
+
+    #[inline]
+    fn multiply(&self, other: &Val) -> Val {
+        self.0.multiply(other)
+    }
+
+    #[inline]
+    fn divide(&self, other: &Val) -> Val {
+        self.0.divide(other)
+    }
+
+    #[inline]
+    fn modulo(&self, other: &Val) -> Val {
+        self.0.modulo(other)
+    }
+
+    #[inline]
+    fn negate(&self) -> Val {
+        self.0.negate()
+    }
+
+    #[inline]
+    fn contains(&self, elem: &Val) -> Val {
+        self.0.contains(elem)
+    }
+
+    #[inline]
+    fn index(&self, index: &Val) -> Val {
+        self.0.index(index)
+    }
+
+    #[inline]
+    fn select(&self, field: &str) -> Val {
+        self.0.select(field)
+    }
+
+    #[inline]
+    fn has_field(&self, field: &str) -> Val {
+        self.0.has_field(field)
+    }
 }
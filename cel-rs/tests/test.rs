@@ -19,3 +19,46 @@ cel_spec::suite!(
     skip_section = "in_map_literal",
     skip_section = "bound",
 );
+
+cel_spec::suite!(
+    name = "timestamps",
+);
+
+cel_spec::suite!(
+    name = "string_ext",
+    library = "cel_rs::ext::Strings",
+);
+
+cel_spec::suite!(
+    name = "math_ext",
+    library = "cel_rs::ext::Math",
+);
+
+cel_spec::suite!(
+    name = "encoders_ext",
+    library = "cel_rs::ext::Encoders",
+);
+
+cel_spec::suite!(
+    name = "bindings_ext",
+);
+
+cel_spec::suite!(
+    name = "fields",
+    // Backquoted field names, such as `{'a-b': 1}.`a-b``, are not parsed yet.
+    skip_section = "quoted_map_fields",
+
+    // These expect cel-go's message, which names the type by its protobuf text.
+    skip_test = "list_field_select_unsupported",
+    skip_test = "int64_field_select_unsupported",
+    // Null map keys are not rejected yet.
+    skip_test = "map_key_null",
+);
+
+cel_spec::suite!(
+    name = "proto2",
+);
+
+cel_spec::suite!(
+    name = "proto3",
+);
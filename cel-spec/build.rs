@@ -14,7 +14,11 @@ fn main() -> Result<()> {
     config.disable_comments(&["."]);
     config.file_descriptor_set_path(out.join("cel.bin"));
     config.compile_protos(
-        &["cel-spec/proto/test/v1/simple.proto"],
+        &[
+            "cel-spec/proto/test/v1/simple.proto",
+            "cel-spec/proto/test/v1/proto2/test_all_types.proto",
+            "cel-spec/proto/test/v1/proto3/test_all_types.proto",
+        ],
         &["cel-spec/proto/", "googleapis/"],
     )?;
 
@@ -59,6 +63,8 @@ fn main() -> Result<()> {
             .arg("-Igoogleapis/")
             .arg("--encode=google.api.expr.test.v1.SimpleTestFile")
             .arg("cel-spec/proto/test/v1/simple.proto")
+            .arg("cel-spec/proto/test/v1/proto2/test_all_types.proto")
+            .arg("cel-spec/proto/test/v1/proto3/test_all_types.proto")
             .stdin(Stdio::from(input))
             .stdout(Stdio::from(output))
             .stderr(Stdio::inherit())
@@ -26,6 +26,9 @@ use google::api::expr::v1alpha1::{ExprValue, expr_value};
 use proc_macro::TokenStream;
 use prost::Message;
 
+/// Descriptors of the conformance test messages, such as `TestAllTypes`.
+const DESCRIPTORS: &str = concat!(env!("OUT_DIR"), "/cel.bin");
+
 fn expand_expr_value(val: ExprValue) -> String {
     match val.kind.unwrap() {
         expr_value::Kind::Value(val) => expand_value(val),
@@ -57,9 +60,33 @@ fn expand_value(val: Value) -> String {
                format!("({}, {}),", expand_value(key), expand_value(value))
             }).collect::<Vec<String>>().join("\n")
         ),
-        value::Kind::ListValue(list) => format!("cel_rs::Val::new_list({})", "Vec::new().into()"),
+        value::Kind::ListValue(list) => format!(
+            "cel_rs::Val::new_list(vec![{}].into())",
+            list.values.into_iter().map(expand_value).collect::<Vec<String>>().join(", ")
+        ),
         value::Kind::EnumValue(en) => "TODO: EnumValue".to_string(),
-        value::Kind::ObjectValue(obj) => "TODO: ObjectValue".to_string(),
+        value::Kind::ObjectValue(obj) => match obj.type_url.as_str() {
+            "type.googleapis.com/google.protobuf.Timestamp" => {
+                let ts = prost_types::Timestamp::decode(obj.value.as_slice()).unwrap();
+                format!("cel_rs::Val::new_timestamp({}, {})", ts.seconds, ts.nanos)
+            }
+            "type.googleapis.com/google.protobuf.Duration" => {
+                let d = prost_types::Duration::decode(obj.value.as_slice()).unwrap();
+                format!("cel_rs::Val::new_duration({}, {})", d.seconds, d.nanos)
+            }
+            type_url => {
+                let name = type_url.rsplit('/').next().unwrap();
+                format!(
+                    "cel_rs::Val::new_message(cel_rs::prost_reflect::DynamicMessage::decode(\
+                        cel_rs::prost_reflect::DescriptorPool::decode(include_bytes!({descriptors:?}).as_ref()).unwrap()\
+                            .get_message_by_name({name:?}).unwrap(),\
+                        &{bytes:?} as &[u8]).unwrap())",
+                    descriptors = DESCRIPTORS,
+                    name = name,
+                    bytes = obj.value,
+                )
+            }
+        },
         value::Kind::TypeValue(ty) => "TODO: TypeValue".to_string(),
     }
 }
@@ -102,6 +129,8 @@ struct MacroArgs {
     skip_sections: Vec<String>,
     #[darling(multiple, rename = "skip_test")]
     skip_tests: Vec<String>,
+    #[darling(multiple, rename = "library")]
+    libraries: Vec<String>,
 }
 
 #[proc_macro]
@@ -141,7 +170,17 @@ pub fn suite(rargs: TokenStream) -> TokenStream {
 
             let expected_value = expand_result_matcher(test.result_matcher);
 
-            let bindings = expand_bindings(test.bindings);
+            let mut bindings = format!(
+                "ctx.add_file_descriptor_set(include_bytes!({:?})).unwrap();",
+                DESCRIPTORS
+            );
+            if !test.container.is_empty() {
+                bindings.push_str(&format!("ctx.container({:?});", test.container));
+            }
+            bindings.push_str(&expand_bindings(test.bindings));
+            for library in &args.libraries {
+                bindings.push_str(&format!("ctx.add_library({});", library));
+            }
 
             ast.push_str(
                 &format!(